@@ -0,0 +1,158 @@
+use crate::codec::Codec;
+use crate::stats::DataStats;
+use crate::streams::{join_streams, split_streams};
+use std::rc::Rc;
+
+/// Number of bytes for which a codec is chosen at a time.
+const AUTO_BLOCK_LEN: usize = 1 << 17;
+
+/// Number of bytes encoded to estimate the size of codecs that can not estimate
+/// it from statistics.
+const AUTO_SAMPLE_LEN: usize = 1 << 14;
+
+/// Chooses the codec for every block of the data automatically. Only the codecs
+/// whose format matches the block are considered. Codecs that can not estimate
+/// their output size from statistics of the block encode a sample of it instead,
+/// and their size is scaled from that. Only the codec with the smallest estimate
+/// encodes the whole block.
+///
+/// The codec ids of the blocks are stored before the encoded blocks, so the
+/// candidates must be the same when decoding.
+pub struct AutoCodec {
+    candidates: Vec<Rc<dyn Codec>>,
+    block_len: usize,
+    sample_len: usize,
+}
+
+impl AutoCodec {
+    pub fn new(candidates: Vec<Rc<dyn Codec>>) -> AutoCodec {
+        AutoCodec {
+            candidates,
+            block_len: AUTO_BLOCK_LEN,
+            sample_len: AUTO_SAMPLE_LEN,
+        }
+    }
+
+    /// Returns the estimated size of the block encoded by the codec.
+    fn estimate_size(&self, codec: &dyn Codec, block: &[u8], stats: &DataStats) -> usize {
+        codec.estimate_size(stats).unwrap_or_else(|| {
+            let sample = &block[..block.len().min(self.sample_len)];
+            if sample.is_empty() {
+                return 0;
+            }
+            codec.encode(sample).len() * block.len() / sample.len()
+        })
+    }
+
+    /// Returns the codec with the smallest estimated size for the block.
+    fn choose_codec(&self, block: &[u8]) -> &Rc<dyn Codec> {
+        let stats = DataStats::new(block);
+        self.candidates
+            .iter()
+            .filter(|codec| codec.matches_format(block))
+            .min_by_key(|codec| self.estimate_size(codec.as_ref(), block, &stats))
+            .expect("AutoCodec has no candidate codecs for the block")
+    }
+}
+
+impl Codec for AutoCodec {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn id(&self) -> u8 {
+        6
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut codec_ids = Vec::new();
+        let mut streams = vec![Vec::new()];
+        for block in data.chunks(self.block_len) {
+            let codec = self.choose_codec(block);
+            codec_ids.push(codec.id());
+            streams.push(codec.encode(block));
+        }
+        streams[0] = codec_ids;
+        join_streams(&streams)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        let mut streams = split_streams(data).into_iter();
+        let codec_ids = streams.next().unwrap_or_default();
+
+        let mut decoded = Vec::new();
+        for (id, block) in codec_ids.iter().zip(streams) {
+            let codec = self
+                .candidates
+                .iter()
+                .find(|codec| codec.id() == *id)
+                .expect("Unknown compression method");
+            decoded.extend(codec.decode(&block));
+        }
+        decoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::CodecRegistry;
+
+    #[test]
+    fn test_auto_codec() {
+        let registry = CodecRegistry::default();
+        let auto = AutoCodec {
+            candidates: registry
+                .codecs()
+                .filter(|codec| codec.name() != "auto")
+                .cloned()
+                .collect(),
+            block_len: 5000,
+            sample_len: 1000,
+        };
+
+        let text = b"to be or not to be, that is the question. ".repeat(120);
+        let input = [text, vec![0; 10_000]].concat();
+
+        let encoded = auto.encode(&input);
+        assert_eq!(auto.decode(&encoded), input);
+        assert!(
+            encoded.len()
+                < registry
+                    .get_by_name("huffman")
+                    .unwrap()
+                    .encode(&input)
+                    .len()
+        );
+
+        let streams = split_streams(&encoded);
+        assert_eq!(streams[0].len(), 4);
+        // the text is a single line, so it is not considered as logs, and the codec
+        // with the smallest estimate is used
+        let text_block = &input[..5000];
+        let logs = registry.get_by_name("logs").unwrap();
+        assert!(!logs.matches_format(text_block));
+        assert_ne!(streams[0][0], logs.id());
+        let stats = DataStats::new(text_block);
+        let estimates: Vec<(usize, u8)> = registry
+            .codecs()
+            .filter(|codec| codec.name() != "auto" && codec.matches_format(text_block))
+            .map(|codec| {
+                (
+                    auto.estimate_size(codec.as_ref(), text_block, &stats),
+                    codec.id(),
+                )
+            })
+            .collect();
+        // the words codec can not estimate its size, so it encodes a sample
+        let words = registry.get_by_name("words").unwrap();
+        assert!(words.estimate_size(&stats).is_none());
+        assert!(estimates.contains(&(words.encode(&text_block[..1000]).len() * 5, words.id())));
+        let smallest = estimates.iter().map(|(size, _)| *size).min().unwrap();
+        assert!(estimates.contains(&(smallest, streams[0][0])));
+        // the third block only contains zeros
+        assert!(streams[3].len() < 100);
+
+        assert!(auto.decode(&auto.encode(&[])).is_empty());
+    }
+}
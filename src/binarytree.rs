@@ -105,7 +105,7 @@ impl<T: Clone> BinaryTree<T> {
 
     /// Returns an iterator iterating over the tree from left to right. Note that
     /// only leaf nodes are returned.
-    pub fn leaves(&self) -> BTreeLeafIter<T> {
+    pub fn leaves(&self) -> BTreeLeafIter<'_, T> {
         let mut current_branch = vec![(self.root_node(), false)];
         while let Some((BTreeNode::Branch { left, .. }, _)) = current_branch.last() {
             current_branch.push((&self.nodes[*left], false));
@@ -256,7 +256,7 @@ mod tests {
     #[test]
     fn test_add_leaf() {
         let mut tree = BinaryTree::new(3u8);
-        let seq = vec![
+        let seq = [
             vec![true],
             vec![false, false],
             vec![false, true, false],
@@ -0,0 +1,155 @@
+use crate::savebits::SaveBits;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Number of symbols that are examined at a time when looking for block boundaries.
+/// Block boundaries can only be placed at multiples of this.
+const SEGMENT_LEN: usize = 1024;
+
+/// Splits the data into blocks which are each compressed with their own tree.
+/// The data is examined one segment at a time, and a new block is started whenever
+/// the estimated cost of coding the segment with a tree of its own is lower than the
+/// estimated cost of adding the segment to the current block.
+pub fn split_blocks<T: SaveBits + Hash + Eq + Clone>(data: &[T]) -> Vec<&[T]> {
+    let mut blocks = Vec::new();
+    let mut block_start = 0;
+    let mut block_uses = Histogram::new();
+
+    for (segment_start, segment) in (0..).step_by(SEGMENT_LEN).zip(data.chunks(SEGMENT_LEN)) {
+        let segment_uses = Histogram::from_symbols(segment);
+
+        if block_uses.total > 0 {
+            let joined_cost = block_uses.merged(&segment_uses).estimate_cost();
+            let split_cost = block_uses.estimate_cost() + segment_uses.estimate_cost();
+            if split_cost < joined_cost {
+                blocks.push(&data[block_start..segment_start]);
+                block_start = segment_start;
+                block_uses = Histogram::new();
+            }
+        }
+
+        block_uses.add(&segment_uses);
+    }
+
+    if block_start < data.len() {
+        blocks.push(&data[block_start..]);
+    }
+
+    blocks
+}
+
+/// The number of times each symbol occurs in a piece of data.
+#[derive(Clone)]
+pub struct Histogram<T: Hash + Eq> {
+    counts: HashMap<T, usize>,
+    total: usize,
+    /// Number of bits needed to save all of the distinct symbols.
+    symbol_bits: usize,
+}
+
+impl<T: SaveBits + Hash + Eq + Clone> Histogram<T> {
+    pub fn new() -> Histogram<T> {
+        Histogram {
+            counts: HashMap::new(),
+            total: 0,
+            symbol_bits: 0,
+        }
+    }
+
+    pub fn from_symbols(data: &[T]) -> Histogram<T> {
+        let mut histogram = Histogram::new();
+        for symbol in data {
+            histogram.add_symbol(symbol, 1);
+        }
+        histogram
+    }
+
+    fn add_symbol(&mut self, symbol: &T, count: usize) {
+        match self.counts.get_mut(symbol) {
+            Some(old_count) => *old_count += count,
+            None => {
+                self.counts.insert(symbol.clone(), count);
+                self.symbol_bits += symbol.save_bits().count();
+            }
+        }
+        self.total += count;
+    }
+
+    pub fn add(&mut self, other: &Histogram<T>) {
+        for (symbol, count) in other.counts.iter() {
+            self.add_symbol(symbol, *count);
+        }
+    }
+
+    pub fn merged(&self, other: &Histogram<T>) -> Histogram<T> {
+        let mut merged = self.clone();
+        merged.add(other);
+        merged
+    }
+
+    /// Estimates the number of bits needed to store the data as a block,
+    /// including the tree and the block length. The data itself is assumed
+    /// to take as many bits as its order-0 entropy.
+    pub fn estimate_cost(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        let total = self.total as f64;
+        let data_bits: f64 = self
+            .counts
+            .values()
+            .map(|&count| count as f64 * (total / count as f64).log2())
+            .sum();
+
+        // every leaf takes a marker bit and the symbol, every branch a marker bit
+        let tree_bits = self.symbol_bits + 2 * self.counts.len() - 1;
+        let length_bits = 2.0 * total.log2().floor() + 1.0;
+
+        data_bits + tree_bits as f64 + length_bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random_bytes(len: usize, alphabet: &[u8]) -> Vec<u8> {
+        let mut state = 12345u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                alphabet[(state >> 16) as usize % alphabet.len()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_blocks_cover_data() {
+        let mut data = pseudo_random_bytes(10_000, b"abcd");
+        data.extend(pseudo_random_bytes(5_000, b"0123456789"));
+        data.extend(pseudo_random_bytes(333, b"xyz"));
+
+        let blocks = split_blocks(&data);
+        assert_eq!(blocks.concat(), data);
+        assert!(blocks.iter().all(|block| !block.is_empty()));
+    }
+
+    #[test]
+    fn test_split_on_distribution_change() {
+        let mut data = pseudo_random_bytes(8 * SEGMENT_LEN, b"abcd");
+        data.extend(pseudo_random_bytes(8 * SEGMENT_LEN, b"wxyz"));
+
+        let blocks = split_blocks(&data);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].len(), 8 * SEGMENT_LEN);
+    }
+
+    #[test]
+    fn test_no_split_on_uniform_data() {
+        let data = pseudo_random_bytes(16 * SEGMENT_LEN, b"abcdefgh");
+
+        assert_eq!(split_blocks(&data).len(), 1);
+        assert!(split_blocks::<u8>(&[]).is_empty());
+    }
+}
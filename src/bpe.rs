@@ -0,0 +1,175 @@
+use crate::codec::Codec;
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::stats::DataStats;
+use crate::streams::{join_streams, split_streams};
+
+/// A pair is only replaced if it occurs at least this many times, since every
+/// substitution takes three bytes to store.
+const MIN_PAIR_COUNT: usize = 4;
+
+/// Byte pair encoding. Repeatedly replaces the most common pair of adjacent bytes
+/// with a byte value that does not occur in the data. Returns the substitutions
+/// in the order they were made as `[replacement, first, second]`, and the data
+/// with the substitutions made.
+pub fn bpe_encode(data: &[u8]) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let mut data = data.to_vec();
+    let mut substitutions = Vec::new();
+
+    loop {
+        let mut byte_counts = [0usize; 256];
+        for byte in data.iter() {
+            byte_counts[*byte as usize] += 1;
+        }
+        let unused = match byte_counts.iter().position(|&count| count == 0) {
+            Some(unused) => unused as u8,
+            None => break,
+        };
+
+        let (first, second) = match most_common_pair(&data) {
+            Some(pair) => pair,
+            None => break,
+        };
+
+        let mut replaced = Vec::with_capacity(data.len());
+        let mut x = 0;
+        while x < data.len() {
+            if x + 1 < data.len() && data[x] == first && data[x + 1] == second {
+                replaced.push(unused);
+                x += 2;
+            } else {
+                replaced.push(data[x]);
+                x += 1;
+            }
+        }
+
+        data = replaced;
+        substitutions.push([unused, first, second]);
+    }
+
+    (substitutions, data)
+}
+
+/// Reverses `bpe_encode` by undoing the substitutions starting from the last one.
+pub fn bpe_decode(substitutions: &[[u8; 3]], data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for [replacement, first, second] in substitutions.iter().rev() {
+        data = data
+            .iter()
+            .flat_map(|byte| {
+                if byte == replacement {
+                    vec![*first, *second]
+                } else {
+                    vec![*byte]
+                }
+            })
+            .collect();
+    }
+    data
+}
+
+/// Finds the pair of adjacent bytes that occurs most often without overlapping itself.
+/// Returns `None` if no pair occurs at least `MIN_PAIR_COUNT` times.
+fn most_common_pair(data: &[u8]) -> Option<(u8, u8)> {
+    let mut pair_counts = vec![0usize; 1 << 16];
+    // the position of the second byte of the last counted pair
+    let mut last_counted = None;
+    for (x, pair) in data.windows(2).enumerate() {
+        let pair_idx = (pair[0] as usize) << 8 | pair[1] as usize;
+        // in a run such as "aaa" only every other pair can be replaced
+        if last_counted == Some((x, pair_idx)) {
+            continue;
+        }
+        pair_counts[pair_idx] += 1;
+        last_counted = Some((x + 1, pair_idx));
+    }
+
+    // the first pair is chosen on ties to make the output deterministic
+    let (pair_idx, count) = pair_counts
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, count)| **count)?;
+    if *count < MIN_PAIR_COUNT {
+        return None;
+    }
+    Some(((pair_idx >> 8) as u8, pair_idx as u8))
+}
+
+/// Compresses the data by byte pair encoding it before Huffman coding. The
+/// substitutions are stored in front of the coded data.
+pub fn compress_bpe(data: &[u8]) -> Vec<u8> {
+    let (substitutions, encoded) = bpe_encode(data);
+    join_streams(&[substitutions.concat(), compress(&encoded)])
+}
+
+pub fn decompress_bpe(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in byte pair data");
+
+    let substitutions: Vec<[u8; 3]> = next_stream()
+        .chunks(3)
+        .map(|s| [s[0], s[1], s[2]])
+        .collect();
+    bpe_decode(&substitutions, &decompress(next_stream()))
+}
+
+/// Codes the bytes after byte pair encoding them.
+pub struct BpeCodec;
+
+impl Codec for BpeCodec {
+    fn name(&self) -> &'static str {
+        "bpe"
+    }
+
+    fn id(&self) -> u8 {
+        3
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_bpe(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_bpe(data)
+    }
+
+    fn estimate_size(&self, stats: &DataStats) -> Option<usize> {
+        // replacing pairs helps roughly as much as the data repeats itself
+        let huffman_bits = stats.byte_entropy + 10.0 * stats.distinct_bytes as f64;
+        Some((huffman_bits * (1.0 - 0.5 * stats.match_ratio) / 8.0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_encode() {
+        let (substitutions, encoded) = bpe_encode(b"abababab-ab");
+        assert_eq!(substitutions, vec![[0, b'a', b'b']]);
+        assert_eq!(encoded, vec![0, 0, 0, 0, b'-', 0]);
+
+        let (substitutions, encoded) = bpe_encode(&[b'a'; 17]);
+        assert_eq!(substitutions, vec![[0, b'a', b'a'], [1, 0, 0]]);
+        assert_eq!(encoded, vec![1, 1, 1, 1, b'a']);
+    }
+
+    #[test]
+    fn test_bpe_encode_decode() {
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        let inputs = [
+            Vec::new(),
+            b"x".to_vec(),
+            b"the theme of the thesis is the theory".repeat(20),
+            all_bytes.repeat(10),
+        ];
+
+        for input in inputs.iter() {
+            let (substitutions, encoded) = bpe_encode(input);
+            assert_eq!(&bpe_decode(&substitutions, &encoded), input);
+            assert_eq!(&decompress_bpe(&compress_bpe(input)), input);
+        }
+    }
+}
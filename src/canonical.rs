@@ -0,0 +1,177 @@
+use bitvec::{order::Lsb0, vec::BitVec};
+
+use crate::compressor::{build_tree, count_uses};
+use crate::savebits::SaveBits;
+use std::convert::TryFrom;
+
+/// Codes are stored in a `u64`, which limits the length of a code.
+const MAX_CODE_LEN: usize = 64;
+
+/// Compresses the symbols with a canonical Huffman code. Since a canonical code is
+/// fully determined by the code lengths of the symbols, only the symbols that occur
+/// and their code lengths are stored. This keeps the header small for large but
+/// sparsely used alphabets.
+pub fn encode_canonical(symbols: &[u32]) -> Vec<u8> {
+    let lengths = code_lengths(symbols);
+    let codes = canonical_codes(&lengths);
+
+    let mut output: BitVec<Lsb0, u8> = BitVec::new();
+    // counts are offset by one, since zero can not be saved
+    output.extend((symbols.len() + 1).save_bits());
+    output.extend((lengths.len() + 1).save_bits());
+    let mut previous_symbol = None;
+    for (symbol, len) in lengths.iter() {
+        // the first symbol is offset by one, which does not fit in a u32 for u32::MAX
+        let delta = match previous_symbol {
+            Some(previous_symbol) => (symbol - previous_symbol) as usize,
+            None => *symbol as usize + 1,
+        };
+        output.extend(delta.save_bits());
+        output.extend((len + 1).save_bits());
+        previous_symbol = Some(*symbol);
+    }
+
+    for symbol in symbols {
+        let (code, len) = codes[lengths.binary_search_by_key(symbol, |(s, _)| *s).unwrap()];
+        output.extend((0..len).rev().map(|x| (code >> x) & 1 == 1));
+    }
+
+    output.into_vec()
+}
+
+pub fn decode_canonical(data: &[u8]) -> Vec<u32> {
+    let bits: BitVec<Lsb0, u8> = BitVec::from_vec(data.to_vec());
+    let mut it = bits.iter().map(|r| *r);
+
+    let num_symbols = usize::from_bits(&mut it) - 1;
+    let num_distinct = usize::from_bits(&mut it) - 1;
+    let mut lengths = Vec::new();
+    let mut previous_symbol = None;
+    for _ in 0..num_distinct {
+        let delta = usize::from_bits(&mut it);
+        let symbol = match previous_symbol {
+            Some(previous_symbol) => previous_symbol as usize + delta,
+            None => delta - 1,
+        };
+        let symbol = u32::try_from(symbol).expect("Invalid symbol in canonical data");
+        lengths.push((symbol, usize::from_bits(&mut it) - 1));
+        previous_symbol = Some(symbol);
+    }
+
+    let decoder = CanonicalDecoder::new(&lengths);
+    (0..num_symbols).map(|_| decoder.decode(&mut it)).collect()
+}
+
+/// Returns the symbols that occur in the data in ascending order, together with
+/// the lengths of their Huffman codes.
+fn code_lengths(symbols: &[u32]) -> Vec<(u32, usize)> {
+    let uses = count_uses(symbols);
+    if uses.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lengths: Vec<(u32, usize)> = build_tree(&mut uses.into_iter())
+        .leaves()
+        .map(|((_, symbol), route)| (*symbol, route.len()))
+        .collect();
+    lengths.sort_unstable();
+    assert!(lengths.iter().all(|(_, len)| *len <= MAX_CODE_LEN));
+    lengths
+}
+
+/// Assigns the canonical codes to the symbols. Shorter codes come before longer ones,
+/// and codes of the same length are ordered by symbol. Returns `(code, length)` for
+/// each symbol in the same order as the lengths.
+fn canonical_codes(lengths: &[(u32, usize)]) -> Vec<(u64, usize)> {
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by_key(|x| (lengths[*x].1, lengths[*x].0));
+
+    let mut codes = vec![(0, 0); lengths.len()];
+    let mut code = 0u64;
+    let mut previous_len = 0;
+    for (x, idx) in order.into_iter().enumerate() {
+        let len = lengths[idx].1;
+        if x > 0 {
+            code = (code + 1) << (len - previous_len);
+        }
+        codes[idx] = (code, len);
+        previous_len = len;
+    }
+    codes
+}
+
+/// Decodes canonical codes one bit at a time using the number of codes of each length.
+struct CanonicalDecoder {
+    /// Symbols ordered by their codes.
+    symbols: Vec<u32>,
+    /// Number of codes of each length.
+    len_counts: Vec<u64>,
+}
+
+impl CanonicalDecoder {
+    fn new(lengths: &[(u32, usize)]) -> CanonicalDecoder {
+        let mut sorted = lengths.to_vec();
+        sorted.sort_by_key(|(symbol, len)| (*len, *symbol));
+
+        let mut len_counts = vec![0; MAX_CODE_LEN + 1];
+        for (_, len) in sorted.iter() {
+            len_counts[*len] += 1;
+        }
+
+        CanonicalDecoder {
+            symbols: sorted.into_iter().map(|(symbol, _)| symbol).collect(),
+            len_counts,
+        }
+    }
+
+    fn decode(&self, iter: &mut impl Iterator<Item = bool>) -> u32 {
+        // the first code and the index of the first symbol with the current length
+        let mut first_code = 0u64;
+        let mut first_idx = 0u64;
+        let mut code = 0u64;
+        for len in 0..=MAX_CODE_LEN {
+            let count = self.len_counts[len];
+            if code - first_code < count {
+                return self.symbols[(first_idx + code - first_code) as usize];
+            }
+            first_idx += count;
+            first_code = (first_code + count) << 1;
+            let bit = iter
+                .next()
+                .expect("Iterator returned None while decoding a canonical code");
+            code = code << 1 | bit as u64;
+        }
+        panic!("Invalid canonical code");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_codes() {
+        let lengths = vec![(1, 3), (2, 2), (5, 3), (7, 1)];
+        assert_eq!(
+            canonical_codes(&lengths),
+            vec![(0b110, 3), (0b10, 2), (0b111, 3), (0b0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_encode_decode_canonical() {
+        let inputs = [
+            Vec::new(),
+            vec![65535],
+            vec![3, 3, 3],
+            vec![0, 1, 0, 2, 0, 1, 0, 3, 70_000, 0],
+            (0..20_000u32).map(|x| x * x % 9973).collect(),
+            vec![u32::MAX],
+            vec![0, u32::MAX, u32::MAX - 1, 0],
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(&decode_canonical(&encode_canonical(input)), input);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::auto::AutoCodec;
+use crate::bpe::BpeCodec;
+use crate::csv::CsvCodec;
+use crate::digram::DigramCodec;
+use crate::fasta::FastaCodec;
+use crate::huffman::HuffmanCodec;
+use crate::json::JsonCodec;
+use crate::logs::LogCodec;
+use crate::pnm::PnmCodec;
+use crate::rle::RleCodec;
+use crate::stats::DataStats;
+use crate::stored::StoredCodec;
+use crate::utf8::Utf8Codec;
+use crate::wav::WavCodec;
+use crate::words::WordCodec;
+use std::rc::Rc;
+
+/// A method for compressing data. A codec is the last stage of a `Pipeline`, and its
+/// id is stored in the first byte of the compressed data, so decompressing does not
+/// need to be told which codec was used.
+pub trait Codec {
+    /// The name of the codec in pipeline specifications.
+    fn name(&self) -> &'static str;
+    /// The id of the codec in compressed data.
+    fn id(&self) -> u8;
+    fn encode(&self, data: &[u8]) -> Vec<u8>;
+    fn decode(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Estimates the size of the encoded data in bytes from statistics of the data,
+    /// without encoding it. Returns `None` if the size can not be estimated cheaply.
+    fn estimate_size(&self, _stats: &DataStats) -> Option<usize> {
+        None
+    }
+
+    /// Whether the data looks like the format the codec is made for. Codecs for
+    /// specific formats are only tried by `AutoCodec` on blocks in their format.
+    fn matches_format(&self, _data: &[u8]) -> bool {
+        true
+    }
+}
+
+/// The codecs that can be used for compressing and decompressing. The default
+/// registry contains all of the codecs of this crate, and more can be added
+/// with `register`.
+pub struct CodecRegistry {
+    codecs: Vec<Rc<dyn Codec>>,
+}
+
+impl CodecRegistry {
+    /// Creates a registry without any codecs.
+    pub fn new() -> CodecRegistry {
+        CodecRegistry { codecs: Vec::new() }
+    }
+
+    /// Adds the codec to the registry. Panics if the name or the id of the codec
+    /// is already used by another codec.
+    pub fn register(&mut self, codec: impl Codec + 'static) {
+        assert!(
+            self.get_by_name(codec.name()).is_none(),
+            "A codec named {} is already registered",
+            codec.name()
+        );
+        assert!(
+            self.get_by_id(codec.id()).is_none(),
+            "A codec with id {} is already registered",
+            codec.id()
+        );
+        self.codecs.push(Rc::new(codec));
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<Rc<dyn Codec>> {
+        self.codecs
+            .iter()
+            .find(|codec| codec.name() == name)
+            .cloned()
+    }
+
+    pub fn get_by_id(&self, id: u8) -> Option<Rc<dyn Codec>> {
+        self.codecs.iter().find(|codec| codec.id() == id).cloned()
+    }
+
+    pub fn codecs(&self) -> impl Iterator<Item = &Rc<dyn Codec>> {
+        self.codecs.iter()
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        let mut registry = CodecRegistry::new();
+        registry.register(HuffmanCodec::<u8>::new());
+        registry.register(WordCodec);
+        registry.register(Utf8Codec);
+        registry.register(BpeCodec);
+        registry.register(DigramCodec);
+        registry.register(RleCodec);
+        registry.register(StoredCodec);
+        registry.register(CsvCodec);
+        registry.register(JsonCodec);
+        registry.register(LogCodec);
+        registry.register(FastaCodec);
+        registry.register(PnmCodec);
+        registry.register(WavCodec);
+        let candidates = registry.codecs().cloned().collect();
+        registry.register(AutoCodec::new(candidates));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Reverse;
+
+    impl Codec for Reverse {
+        fn name(&self) -> &'static str {
+            "reverse"
+        }
+
+        fn id(&self) -> u8 {
+            200
+        }
+
+        fn encode(&self, data: &[u8]) -> Vec<u8> {
+            data.iter().rev().copied().collect()
+        }
+
+        fn decode(&self, data: &[u8]) -> Vec<u8> {
+            data.iter().rev().copied().collect()
+        }
+    }
+
+    #[test]
+    fn test_default_codecs() {
+        let registry = CodecRegistry::default();
+        let input = b"one fish two fish red fish blue fish".repeat(10);
+
+        for codec in registry.codecs() {
+            assert_eq!(registry.get_by_name(codec.name()).unwrap().id(), codec.id());
+            assert_eq!(registry.get_by_id(codec.id()).unwrap().name(), codec.name());
+            assert_eq!(codec.decode(&codec.encode(&input)), input);
+        }
+        assert!(registry.get_by_name("unknown").is_none());
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = CodecRegistry::default();
+        registry.register(Reverse);
+
+        let codec = registry.get_by_name("reverse").unwrap();
+        assert_eq!(codec.encode(b"abc"), b"cba");
+        assert_eq!(registry.get_by_id(200).unwrap().name(), "reverse");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_register_duplicate() {
+        let mut registry = CodecRegistry::new();
+        registry.register(Reverse);
+        registry.register(Reverse);
+    }
+}
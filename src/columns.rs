@@ -0,0 +1,333 @@
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::huffman::HuffmanCodec;
+use crate::intcodec::{zigzag_decode, zigzag_encode, BitPacking, IntCodec};
+use crate::streams::{join_streams, split_streams};
+use std::collections::HashMap;
+
+/// Numbers with more digits than this are not parsed, so that they fit in an `i64`.
+const MAX_DIGITS: usize = 18;
+
+/// The type of a column, which decides how the column is coded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    /// Integers without leading zeros, coded as the differences between
+    /// consecutive values.
+    Integer = 0,
+    /// Decimal numbers, coded as integers and the number of decimals of every field.
+    Float = 1,
+    /// Fields with few distinct values, coded as indices to a dictionary.
+    Categorical = 2,
+    /// Anything else, coded as the lengths of the fields and their bytes.
+    Text = 3,
+}
+
+fn detect_column_type(column: &[&[u8]]) -> ColumnType {
+    if column.iter().all(|field| parse_integer(field).is_some()) {
+        return ColumnType::Integer;
+    }
+    if column.iter().all(|field| parse_decimal(field).is_some()) {
+        return ColumnType::Float;
+    }
+
+    let mut distinct: Vec<&[u8]> = column.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if 2 * distinct.len() <= column.len() {
+        ColumnType::Categorical
+    } else {
+        ColumnType::Text
+    }
+}
+
+/// Parses an integer without leading zeros. Zero is not allowed to have a sign,
+/// so that every value has only one representation.
+fn parse_integer(field: &[u8]) -> Option<i64> {
+    let digits = field.strip_prefix(b"-").unwrap_or(field);
+    let is_canonical = !digits.is_empty()
+        && digits.len() <= MAX_DIGITS
+        && digits.iter().all(u8::is_ascii_digit)
+        && (digits[0] != b'0' || field == b"0");
+    if !is_canonical {
+        return None;
+    }
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+/// Parses a number that can have a fraction. Returns the digits of the number as an
+/// integer and the number of digits after the decimal point, or `None` if the
+/// number can not be restored exactly from those.
+fn parse_decimal(field: &[u8]) -> Option<(i64, usize)> {
+    let (whole, fraction) = match field.iter().position(|byte| *byte == b'.') {
+        Some(x) => (&field[..x], &field[x + 1..]),
+        None => return parse_integer(field).map(|value| (value, 0)),
+    };
+    if fraction.is_empty()
+        || fraction.len() > MAX_DIGITS
+        || !fraction.iter().all(u8::is_ascii_digit)
+    {
+        return None;
+    }
+
+    let is_negative = whole.starts_with(b"-");
+    let whole_value = if whole == b"-0" {
+        0
+    } else {
+        parse_integer(whole)?
+    };
+    let fraction_value = fraction
+        .iter()
+        .fold(0, |value, digit| value * 10 + (digit - b'0') as i64);
+
+    let scale = 10i64.checked_pow(fraction.len() as u32)?;
+    let magnitude = whole_value
+        .checked_abs()?
+        .checked_mul(scale)?
+        .checked_add(fraction_value)?;
+    if is_negative && magnitude == 0 {
+        return None;
+    }
+    Some((
+        if is_negative { -magnitude } else { magnitude },
+        fraction.len(),
+    ))
+}
+
+fn format_decimal(value: i64, num_decimals: usize) -> Vec<u8> {
+    if num_decimals == 0 {
+        return value.to_string().into_bytes();
+    }
+    let scale = 10u64.pow(num_decimals as u32);
+    let magnitude = value.unsigned_abs();
+    let sign = if value < 0 { "-" } else { "" };
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        magnitude / scale,
+        magnitude % scale,
+        width = num_decimals
+    )
+    .into_bytes()
+}
+
+/// Codes a column of fields, such as the values of a table column, according to the
+/// type detected for it. Every column is coded with trees of its own.
+pub fn encode_column(column: &[&[u8]]) -> Vec<u8> {
+    let column_type = detect_column_type(column);
+    let usize_codec = HuffmanCodec::<usize>::new();
+    let streams = match column_type {
+        ColumnType::Integer => {
+            let values: Vec<i64> = column
+                .iter()
+                .map(|field| parse_integer(field).unwrap())
+                .collect();
+            vec![encode_numbers(&values)]
+        }
+        ColumnType::Float => {
+            let (values, num_decimals): (Vec<i64>, Vec<usize>) = column
+                .iter()
+                .map(|field| parse_decimal(field).unwrap())
+                .unzip();
+            let num_decimals: Vec<usize> = num_decimals.iter().map(|x| x + 1).collect();
+            vec![encode_numbers(&values), usize_codec.encode(&num_decimals)]
+        }
+        ColumnType::Categorical => {
+            let mut num_uses = HashMap::new();
+            for field in column {
+                *num_uses.entry(*field).or_insert(0) += 1;
+            }
+            let mut dictionary: Vec<(usize, &[u8])> = num_uses
+                .into_iter()
+                .map(|(field, count)| (count, field))
+                .collect();
+            // sort in descending order
+            dictionary.sort_by(|a, b| b.cmp(a));
+
+            // indices start from one, since zero can not be saved
+            let indices: HashMap<&[u8], usize> = dictionary
+                .iter()
+                .zip(1..)
+                .map(|((_, field), idx)| (*field, idx))
+                .collect();
+            let entries: Vec<&[u8]> = dictionary.iter().map(|(_, field)| *field).collect();
+            let field_indices: Vec<usize> = column.iter().map(|field| indices[field]).collect();
+
+            let mut streams = encode_fields(&entries);
+            streams.push(usize_codec.encode(&field_indices));
+            streams
+        }
+        ColumnType::Text => encode_fields(column),
+    };
+
+    let mut output = vec![column_type as u8];
+    output.extend(join_streams(&streams));
+    output
+}
+
+/// Decodes a column of `len` fields coded with `encode_column`.
+pub fn decode_column(data: &[u8], len: usize) -> Vec<Vec<u8>> {
+    let (column_type, data) = data.split_first().expect("Column data is empty");
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in column");
+    let usize_codec = HuffmanCodec::<usize>::new();
+
+    let fields: Vec<Vec<u8>> = match *column_type {
+        x if x == ColumnType::Integer as u8 => decode_numbers(&next_stream())
+            .into_iter()
+            .map(|value| format_decimal(value, 0))
+            .collect(),
+        x if x == ColumnType::Float as u8 => {
+            let values = decode_numbers(&next_stream());
+            let num_decimals = usize_codec.decode(next_stream());
+            values
+                .into_iter()
+                .zip(num_decimals)
+                .map(|(value, num_decimals)| format_decimal(value, num_decimals - 1))
+                .collect()
+        }
+        x if x == ColumnType::Categorical as u8 => {
+            let entries = decode_fields(next_stream(), next_stream());
+            usize_codec
+                .decode(next_stream())
+                .into_iter()
+                .map(|idx| entries[idx - 1].clone())
+                .collect()
+        }
+        x if x == ColumnType::Text as u8 => decode_fields(next_stream(), next_stream()),
+        _ => panic!("Unknown column type"),
+    };
+    assert_eq!(fields.len(), len, "Column has a wrong number of fields");
+    fields
+}
+
+/// Codes the lengths of the fields and their bytes separately.
+fn encode_fields(fields: &[&[u8]]) -> Vec<Vec<u8>> {
+    // lengths are offset by one, since zero can not be saved
+    let lens: Vec<usize> = fields.iter().map(|field| field.len() + 1).collect();
+    vec![
+        HuffmanCodec::new().encode(&lens),
+        compress(&fields.concat()),
+    ]
+}
+
+fn decode_fields(lens: Vec<u8>, bytes: Vec<u8>) -> Vec<Vec<u8>> {
+    let bytes = decompress(bytes);
+    let mut start = 0;
+    HuffmanCodec::<usize>::new()
+        .decode(lens)
+        .into_iter()
+        .map(|len| {
+            let field = bytes[start..start + len - 1].to_vec();
+            start += len - 1;
+            field
+        })
+        .collect()
+}
+
+/// Codes the differences between consecutive numbers. The differences are Huffman
+/// coded, or bit-packed if that is smaller, as it is for columns of unique values.
+fn encode_numbers(values: &[i64]) -> Vec<u8> {
+    let mut previous = 0i64;
+    let deltas: Vec<u64> = values
+        .iter()
+        .map(|value| {
+            let delta = zigzag_encode(value.wrapping_sub(previous));
+            previous = *value;
+            delta
+        })
+        .collect();
+
+    let huffman = HuffmanCodec::new().encode(&deltas);
+    let packed = BitPacking.encode(&deltas);
+    let (method, encoded) = if huffman.len() <= packed.len() {
+        (0, huffman)
+    } else {
+        (1, packed)
+    };
+    let mut output = vec![method];
+    output.extend(encoded);
+    output
+}
+
+fn decode_numbers(data: &[u8]) -> Vec<i64> {
+    let deltas = match data.split_first() {
+        Some((0, encoded)) => HuffmanCodec::<u64>::new().decode(encoded.to_vec()),
+        Some((1, encoded)) => BitPacking.decode(encoded),
+        _ => panic!("Unknown number coding"),
+    };
+
+    let mut previous = 0i64;
+    deltas
+        .into_iter()
+        .map(|delta| {
+            previous = previous.wrapping_add(zigzag_decode(delta));
+            previous
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_column_type() {
+        assert_eq!(
+            detect_column_type(&[b"1", b"-20", b"0"]),
+            ColumnType::Integer
+        );
+        assert_eq!(
+            detect_column_type(&[b"1", b"2.50", b"-0.5"]),
+            ColumnType::Float
+        );
+        assert_eq!(detect_column_type(&[b"1", b"01"]), ColumnType::Text);
+        assert_eq!(detect_column_type(&[b"-0", b"-0"]), ColumnType::Categorical);
+        assert_eq!(detect_column_type(&[b"1e5", b"1.", b"x"]), ColumnType::Text);
+    }
+
+    #[test]
+    fn test_encode_decode_column() {
+        let columns: [&[&[u8]]; 5] = [
+            &[],
+            &[b"", b""],
+            &[b"007", b"120", b"000"],
+            &[b"12:05:00", b"12:05:59", b"23:00:01"],
+            &[b"id1", b"2x", b"", b"id0003"],
+        ];
+        for column in columns.iter() {
+            let decoded = decode_column(&encode_column(column), column.len());
+            assert_eq!(decoded, column.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_decimals() {
+        for field in [
+            "0.5",
+            "-0.05",
+            "12.000",
+            "-3.14",
+            "7",
+            "0.000",
+            "123456.000000000001",
+        ]
+        .iter()
+        {
+            let (value, num_decimals) = parse_decimal(field.as_bytes()).unwrap();
+            assert_eq!(format_decimal(value, num_decimals), field.as_bytes());
+        }
+        for field in [
+            "-0.0",
+            ".5",
+            "5.",
+            "1.2.3",
+            "+1.0",
+            "00.1",
+            "1.0000000000000000000",
+        ]
+        .iter()
+        {
+            assert!(parse_decimal(field.as_bytes()).is_none());
+        }
+    }
+}
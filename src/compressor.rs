@@ -1,31 +1,28 @@
 use bitvec::{order::Lsb0, vec::BitVec};
 
 use crate::binarytree::BinaryTree;
+use crate::blocksplit::split_blocks;
 use crate::savebits::SaveBits;
 use std::collections::HashMap;
+use std::hash::Hash;
 
 pub fn compress(data: &[u8]) -> Vec<u8> {
-    let uses = count_uses(data);
-
-    let tree = build_tree(&mut uses.iter().copied());
-
-    let mut map = HashMap::new();
-
-    for (leaf, route) in tree.leaves() {
-        map.insert(leaf.1, route);
-    }
+    compress_symbols(data)
+}
 
-    let mut compressed: BitVec<Lsb0, u8> = BitVec::new();
-    compressed.extend(
-        data.iter()
-            .map(|byte| map.get(byte).unwrap().iter())
-            .flatten(),
-    );
+/// Compresses a sequence of arbitrary symbols. The symbols are saved in the trees
+/// using their `SaveBits` implementation.
+pub fn compress_symbols<T: SaveBits + Hash + Ord + Clone>(data: &[T]) -> Vec<u8> {
+    let blocks = split_blocks(data);
 
     let mut compression_output: BitVec<Lsb0, u8> = BitVec::new();
-    compression_output.extend(std::iter::repeat(false).take(3));
-    compression_output.extend(tree.map_values(&|(_, byte)| byte).save_bits());
-    compression_output.extend_from_bitslice(compressed.as_bitslice());
+    compression_output.resize(3, false);
+    // the number of blocks is offset by one, since zero can not be saved
+    compression_output.extend((blocks.len() + 1).save_bits());
+    for block in blocks {
+        compression_output.extend(block.len().save_bits());
+        compress_block(block, &mut compression_output);
+    }
 
     let padding = 8 * compression_output.elements() - compression_output.len();
 
@@ -36,25 +33,264 @@ pub fn compress(data: &[u8]) -> Vec<u8> {
     compression_output.into_vec()
 }
 
-fn count_uses(data: &[u8]) -> Vec<(usize, u8)> {
+/// Number of symbols in a segment. Every segment of a block is coded with the
+/// tree that is the cheapest for it.
+pub const SELECTOR_SEGMENT_LEN: usize = 50;
+
+/// Number of times the trees are rebuilt from the segments that selected them.
+const TREE_SELECTION_ITERATIONS: usize = 4;
+
+/// Largest alphabet for which multiple trees are tried. Every tree stores the whole
+/// alphabet, so with larger alphabets the extra trees rarely pay for themselves,
+/// and building them takes most of the time.
+const MAX_MULTIPLE_TREE_ALPHABET_LEN: usize = 256;
+
+/// Appends the number of trees, the trees, the tree selector of every segment
+/// and finally the encoded block to the output.
+fn compress_block<T: SaveBits + Hash + Ord + Clone>(block: &[T], output: &mut BitVec<Lsb0, u8>) {
+    let segments: Vec<&[T]> = block.chunks(SELECTOR_SEGMENT_LEN).collect();
+
+    let single = select_trees(&segments, 1);
+    let alphabet_len = single.trees[0].leaves().count();
+    let selection = if alphabet_len > MAX_MULTIPLE_TREE_ALPHABET_LEN {
+        single
+    } else {
+        let multiple = select_trees(&segments, num_candidate_trees(block.len()));
+        if multiple.cost < single.cost {
+            multiple
+        } else {
+            single
+        }
+    };
+
+    output.extend(selection.trees.len().save_bits());
+    for tree in &selection.trees {
+        output.extend(tree.save_bits());
+    }
+
+    let selector_width = selector_width(selection.trees.len());
+    for selector in &selection.selectors {
+        output.extend((0..selector_width).rev().map(|x| (selector >> x) & 1 == 1));
+    }
+
+    for (segment, selector) in segments.iter().zip(&selection.selectors) {
+        let map = &selection.maps[*selector];
+        output.extend(
+            segment
+                .iter()
+                .flat_map(|byte| map.get(byte).unwrap().iter()),
+        );
+    }
+}
+
+/// Number of bits used to store the index of the tree a segment is coded with.
+pub fn selector_width(num_trees: usize) -> usize {
+    (usize::BITS - (num_trees - 1).leading_zeros()) as usize
+}
+
+/// Number of trees to try for a block. Mirrors the table sizes used by bzip2.
+fn num_candidate_trees(block_len: usize) -> usize {
+    match block_len {
+        0..=199 => 2,
+        200..=599 => 3,
+        600..=1199 => 4,
+        1200..=2399 => 5,
+        _ => 6,
+    }
+}
+
+struct TreeSelection<T: Clone> {
+    trees: Vec<BinaryTree<T>>,
+    maps: Vec<HashMap<T, Vec<bool>>>,
+    selectors: Vec<usize>,
+    /// Total number of bits needed for the trees, selectors and coded segments.
+    cost: usize,
+}
+
+/// Builds `num_trees` trees for the segments and selects a tree for every segment.
+/// The segments are first divided evenly between the trees, after which the trees
+/// are repeatedly rebuilt from the segments that selected them and every segment
+/// selects the tree that codes it with the fewest bits, until the selections stop
+/// changing. Every tree contains all of the symbols in the block, so any segment can
+/// be coded with any tree.
+///
+/// While selecting, the symbols are replaced by their ranks in the sorted alphabet,
+/// so that the segments can be scored from their symbol counts and the code lengths
+/// of the trees without hashing the symbols.
+fn select_trees<T: SaveBits + Hash + Ord + Clone>(
+    segments: &[&[T]],
+    num_trees: usize,
+) -> TreeSelection<T> {
+    let num_trees = num_trees.min(segments.len());
+    let mut alphabet: Vec<T> = count_uses(&segments.concat())
+        .into_iter()
+        .map(|(_, symbol)| symbol)
+        .collect();
+    alphabet.sort_unstable();
+    let ranks: HashMap<&T, usize> = alphabet
+        .iter()
+        .enumerate()
+        .map(|(rank, symbol)| (symbol, rank))
+        .collect();
+    let segment_uses: Vec<Vec<(usize, usize)>> = segments
+        .iter()
+        .map(|segment| {
+            let mut segment_ranks: Vec<usize> =
+                segment.iter().map(|symbol| ranks[symbol]).collect();
+            segment_ranks.sort_unstable();
+            let mut uses: Vec<(usize, usize)> = Vec::new();
+            for rank in segment_ranks {
+                match uses.last_mut() {
+                    Some((last, count)) if *last == rank => *count += 1,
+                    _ => uses.push((rank, 1)),
+                }
+            }
+            uses
+        })
+        .collect();
+
+    let mut selectors: Vec<usize> = (0..segments.len())
+        .map(|x| x * num_trees / segments.len())
+        .collect();
+    let mut trees = Vec::new();
+
+    for _ in 0..TREE_SELECTION_ITERATIONS {
+        trees = (0..num_trees)
+            .map(|tree_idx| {
+                let group = segment_uses
+                    .iter()
+                    .zip(&selectors)
+                    .filter(|(_, selector)| **selector == tree_idx)
+                    .map(|(uses, _)| uses);
+                build_group_tree(group, alphabet.len())
+            })
+            .collect();
+        let code_lens: Vec<Vec<usize>> = trees.iter().map(code_lens).collect();
+
+        let new_selectors: Vec<usize> = segment_uses
+            .iter()
+            .map(|uses| {
+                (0..num_trees)
+                    .min_by_key(|tree_idx| coded_len(uses, &code_lens[*tree_idx]))
+                    .unwrap()
+            })
+            .collect();
+        if new_selectors == selectors {
+            break;
+        }
+        selectors = new_selectors;
+    }
+
+    // drop the trees no segment selected
+    let mut new_indices = Vec::new();
+    let mut next_idx = 0;
+    for tree_idx in 0..num_trees {
+        new_indices.push(next_idx);
+        if selectors.contains(&tree_idx) {
+            trees.swap(next_idx, tree_idx);
+            next_idx += 1;
+        }
+    }
+    trees.truncate(next_idx);
+    for selector in &mut selectors {
+        *selector = new_indices[*selector];
+    }
+
+    let code_lens: Vec<Vec<usize>> = trees.iter().map(code_lens).collect();
+    let coded_bits: usize = segment_uses
+        .iter()
+        .zip(&selectors)
+        .map(|(uses, selector)| coded_len(uses, &code_lens[*selector]))
+        .sum();
+    let trees: Vec<BinaryTree<T>> = trees
+        .into_iter()
+        .map(|tree| tree.map_values(&|rank| alphabet[rank].clone()))
+        .collect();
+    let maps = trees.iter().map(tree_to_map).collect();
+    let cost = trees
+        .iter()
+        .map(|tree| tree.save_bits().count())
+        .sum::<usize>()
+        + selectors.len() * selector_width(trees.len())
+        + coded_bits;
+
+    TreeSelection {
+        trees,
+        maps,
+        selectors,
+        cost,
+    }
+}
+
+/// Builds a tree of symbol ranks from the symbol counts of the segments. Symbols of
+/// the alphabet which do not occur in the segments are counted as if they occurred
+/// once.
+fn build_group_tree<'a>(
+    segment_uses: impl Iterator<Item = &'a Vec<(usize, usize)>>,
+    alphabet_len: usize,
+) -> BinaryTree<usize> {
+    let mut num_uses = vec![0; alphabet_len];
+    for uses in segment_uses {
+        for (rank, count) in uses {
+            num_uses[*rank] += count;
+        }
+    }
+
+    let mut uses: Vec<(usize, usize)> = num_uses
+        .into_iter()
+        .enumerate()
+        .map(|(rank, count)| (count.max(1), rank))
+        .collect();
+    // sort in descending order
+    uses.sort_by(|a, b| b.cmp(a));
+
+    build_tree(&mut uses.into_iter()).map_values(&|(_, rank)| rank)
+}
+
+/// Returns the code length of every symbol rank in the tree.
+fn code_lens(tree: &BinaryTree<usize>) -> Vec<usize> {
+    let mut lens = vec![0; tree.leaves().count()];
+    for (rank, route) in tree.leaves() {
+        lens[*rank] = route.len();
+    }
+    lens
+}
+
+fn tree_to_map<T: Hash + Eq + Clone>(tree: &BinaryTree<T>) -> HashMap<T, Vec<bool>> {
+    tree.leaves()
+        .map(|(symbol, route)| (symbol.clone(), route))
+        .collect()
+}
+
+/// Number of bits needed to code a segment with the given symbol counts with the
+/// code lengths of a tree.
+fn coded_len(uses: &[(usize, usize)], code_lens: &[usize]) -> usize {
+    uses.iter()
+        .map(|(rank, count)| count * code_lens[*rank])
+        .sum()
+}
+
+pub fn count_uses<T: Hash + Ord + Clone>(data: &[T]) -> Vec<(usize, T)> {
     let mut num_uses = HashMap::new();
-    for byte in data {
-        *num_uses.entry(*byte).or_insert(0) += 1;
+    for symbol in data {
+        *num_uses.entry(symbol).or_insert(0) += 1;
     }
 
     let mut uses = Vec::new();
-    for (byte, count) in num_uses.iter() {
-        uses.push((*count, *byte));
+    for (symbol, count) in num_uses.iter() {
+        uses.push((*count, (*symbol).clone()));
     }
     // sort in descending order
-    uses.sort_by(|a, b| b.cmp(&a));
+    uses.sort_by(|a, b| b.cmp(a));
     uses
 }
 
 // TODO actually invent some good algorithm
-fn build_tree(uses: &mut impl Iterator<Item = (usize, u8)>) -> BinaryTree<(usize, u8)> {
+pub fn build_tree<T: Ord + Clone>(
+    uses: &mut impl Iterator<Item = (usize, T)>,
+) -> BinaryTree<(usize, T)> {
     let mut tree = BinaryTree::new(uses.next().unwrap());
-    for (next_count, next_byte) in uses {
+    for (next_count, next_symbol) in uses {
         let route = tree
             .leaves()
             .map(|((count, _), r)| (count + r.len() * next_count, r))
@@ -62,9 +298,47 @@ fn build_tree(uses: &mut impl Iterator<Item = (usize, u8)>) -> BinaryTree<(usize
             .unwrap()
             .1;
         tree.add_leaf(
-            (next_count, next_byte),
+            (next_count, next_symbol),
             &mut route.iter().copied().chain(std::iter::once(false)),
         );
     }
     tree
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_trees_alternating_distributions() {
+        let block: Vec<u8> = (0..40)
+            .flat_map(|x| {
+                if x % 2 == 0 {
+                    b"abababaabbabaababbbaabababaabbabaababbbaabababaabb".iter()
+                } else {
+                    b"xyzzyxyzxyzzyzxxyzyxzyzxyzzyxyzxyzzyzxxyzyxzyzxyz".iter()
+                }
+            })
+            .copied()
+            .collect();
+        let segments: Vec<&[u8]> = block.chunks(SELECTOR_SEGMENT_LEN).collect();
+
+        let single = select_trees(&segments, 1);
+        let multiple = select_trees(&segments, 2);
+
+        assert_eq!(single.trees.len(), 1);
+        assert_eq!(multiple.trees.len(), 2);
+        assert!(multiple.cost < single.cost);
+        for (x, selector) in multiple.selectors.iter().enumerate() {
+            assert_eq!(*selector, multiple.selectors[x % 2]);
+        }
+    }
+
+    #[test]
+    fn test_selector_width() {
+        assert_eq!(selector_width(1), 0);
+        assert_eq!(selector_width(2), 1);
+        assert_eq!(selector_width(4), 2);
+        assert_eq!(selector_width(6), 3);
+    }
+}
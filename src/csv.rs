@@ -0,0 +1,263 @@
+use crate::codec::Codec;
+use crate::columns::{decode_column, encode_column};
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::huffman::HuffmanCodec;
+use crate::streams::{join_streams, split_streams};
+
+/// How a row of the table ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Terminator {
+    Lf,
+    CrLf,
+    /// The last row of the data may not end in a line break.
+    None,
+}
+
+struct Row<'a> {
+    fields: Vec<&'a [u8]>,
+    terminator: Terminator,
+}
+
+/// Compresses CSV or TSV data by columns. The rows are parsed and transposed into
+/// columns, and every column is coded according to its type with trees of its own.
+/// Quoted fields are kept as they are, and the line breaks of the rows are stored,
+/// so that any data is reproduced exactly. The first line is stored as it is,
+/// since it usually holds the names of the columns.
+pub fn compress_csv(data: &[u8]) -> Vec<u8> {
+    let header_len = data
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(data.len(), |x| x + 1);
+    let (header, body) = data.split_at(header_len);
+    let delimiter = detect_delimiter(header);
+    let rows = parse_rows(body, delimiter);
+
+    let num_fields: Vec<usize> = rows.iter().map(|row| row.fields.len()).collect();
+    let terminators: Vec<usize> = rows.iter().map(|row| row.terminator as usize + 1).collect();
+
+    let usize_codec = HuffmanCodec::<usize>::new();
+    let mut streams = vec![
+        vec![delimiter],
+        compress(header),
+        usize_codec.encode(&num_fields),
+        usize_codec.encode(&terminators),
+    ];
+
+    let num_columns = num_fields.iter().copied().max().unwrap_or(0);
+    for column_idx in 0..num_columns {
+        let column: Vec<&[u8]> = rows
+            .iter()
+            .filter_map(|row| row.fields.get(column_idx).copied())
+            .collect();
+        streams.push(encode_column(&column));
+    }
+
+    join_streams(&streams)
+}
+
+pub fn decompress_csv(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in CSV data");
+
+    let delimiter = next_stream()[0];
+    let mut output = decompress(next_stream());
+    let usize_codec = HuffmanCodec::<usize>::new();
+    let num_fields = usize_codec.decode(next_stream());
+    let terminators = usize_codec.decode(next_stream());
+
+    let num_columns = num_fields.iter().copied().max().unwrap_or(0);
+    let mut columns: Vec<_> = (0..num_columns)
+        .map(|column_idx| {
+            let len = num_fields.iter().filter(|x| **x > column_idx).count();
+            decode_column(&next_stream(), len).into_iter()
+        })
+        .collect();
+
+    for (row_len, terminator) in num_fields.iter().zip(terminators) {
+        for (x, column) in columns.iter_mut().take(*row_len).enumerate() {
+            if x > 0 {
+                output.push(delimiter);
+            }
+            output.extend(column.next().expect("Column has too few fields"));
+        }
+        match terminator - 1 {
+            x if x == Terminator::Lf as usize => output.push(b'\n'),
+            x if x == Terminator::CrLf as usize => output.extend_from_slice(b"\r\n"),
+            _ => {}
+        }
+    }
+    output
+}
+
+/// Number of rows that must have the same number of fields for data to be a table.
+const MIN_TABLE_ROWS: usize = 3;
+
+/// Only this many bytes are parsed when checking whether data is a table.
+const TABLE_CHECK_LEN: usize = 1 << 12;
+
+/// Whether the first rows of the data have the same number of fields, and more than
+/// one. The first line and the row after it are skipped, since a block of a larger
+/// table may start in the middle of a row or of a quoted field.
+fn is_table(data: &[u8]) -> bool {
+    let body = match data.iter().position(|byte| *byte == b'\n') {
+        Some(x) => &data[x + 1..],
+        None => return false,
+    };
+    let body = &body[..body.len().min(TABLE_CHECK_LEN)];
+    let first_line = body.split(|byte| *byte == b'\n').next().unwrap_or_default();
+    let rows = parse_rows(body, detect_delimiter(first_line));
+    let num_fields: Vec<usize> = rows
+        .iter()
+        .filter(|row| row.terminator != Terminator::None)
+        .skip(1)
+        .take(MIN_TABLE_ROWS)
+        .map(|row| row.fields.len())
+        .collect();
+    num_fields.len() == MIN_TABLE_ROWS
+        && num_fields[0] > 1
+        && num_fields.iter().all(|len| *len == num_fields[0])
+}
+
+/// Tabs are used as the delimiter if the line has more of them than commas.
+fn detect_delimiter(line: &[u8]) -> u8 {
+    let count = |delimiter| line.iter().filter(|byte| **byte == delimiter).count();
+    if count(b'\t') > count(b',') {
+        b'\t'
+    } else {
+        b','
+    }
+}
+
+/// Splits the data into rows and the rows into fields. A field that starts with a
+/// quote continues over delimiters and line breaks until the quote is closed.
+fn parse_rows(data: &[u8], delimiter: u8) -> Vec<Row<'_>> {
+    let mut rows = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut fields = Vec::new();
+        let terminator = loop {
+            let start = pos;
+            let is_quoted = data.get(start) == Some(&b'"');
+            let mut in_quotes = false;
+            while pos < data.len() {
+                let byte = data[pos];
+                if is_quoted && byte == b'"' {
+                    in_quotes = !in_quotes;
+                } else if !in_quotes && (byte == delimiter || byte == b'\n') {
+                    break;
+                }
+                pos += 1;
+            }
+
+            if pos == data.len() {
+                fields.push(&data[start..pos]);
+                break Terminator::None;
+            } else if data[pos] == delimiter {
+                fields.push(&data[start..pos]);
+                pos += 1;
+            } else if pos > start && data[pos - 1] == b'\r' {
+                fields.push(&data[start..pos - 1]);
+                pos += 1;
+                break Terminator::CrLf;
+            } else {
+                fields.push(&data[start..pos]);
+                pos += 1;
+                break Terminator::Lf;
+            }
+        };
+        rows.push(Row { fields, terminator });
+    }
+    rows
+}
+
+/// Codes CSV and TSV tables by columns.
+pub struct CsvCodec;
+
+impl Codec for CsvCodec {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn id(&self) -> u8 {
+        8
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_csv(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_csv(data)
+    }
+
+    fn matches_format(&self, data: &[u8]) -> bool {
+        is_table(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Vec<u8> {
+        let mut table = b"id,city,temperature,note\n".to_vec();
+        for x in 0..500 {
+            let city = ["Helsinki", "Oulu", "\"Turku, FI\""][x % 3];
+            let temperature = format!("{}.{}", (x % 30) as i32 - 10, x % 10);
+            let note = if x % 7 == 0 {
+                format!("\"line\nbreak {}\"", x)
+            } else {
+                format!("note {}", x * 37 % 101)
+            };
+            let line = format!("{},{},{},{}\n", 1000 + x, city, temperature, note);
+            table.extend(line.as_bytes());
+        }
+        table
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let rows = parse_rows(b"a,\"b,\"\"c\"\"\nd\",e\r\n\nf,", b',');
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].fields, vec![&b"a"[..], b"\"b,\"\"c\"\"\nd\"", b"e"]);
+        assert_eq!(rows[0].terminator, Terminator::CrLf);
+        assert_eq!(rows[1].fields, vec![&b""[..]]);
+        assert_eq!(rows[1].terminator, Terminator::Lf);
+        assert_eq!(rows[2].fields, vec![&b"f"[..], b""]);
+        assert_eq!(rows[2].terminator, Terminator::None);
+    }
+
+    #[test]
+    fn test_compress_decompress_csv() {
+        let table = table();
+        let tsv = String::from_utf8(table.clone())
+            .unwrap()
+            .replace(',', "\t")
+            .replace('\n', "\r\n");
+        let inputs = [
+            b"".to_vec(),
+            b"header only".to_vec(),
+            b"a,b\n1,2".to_vec(),
+            b"a,b\n1,2,3\n\n4\n\"unterminated,5".to_vec(),
+            b"x\n-9223372036854775808\n9223372036854775807\n".to_vec(),
+            table.clone(),
+            tsv.into_bytes(),
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&decompress_csv(&compress_csv(input)), input);
+        }
+
+        assert!(compress_csv(&table).len() < compress(&table).len() / 2);
+    }
+
+    #[test]
+    fn test_matches_format() {
+        let table = table();
+        assert!(CsvCodec.matches_format(&table));
+        assert!(CsvCodec.matches_format(&table[30..]));
+        assert!(CsvCodec.matches_format(b"a,b\n1,2\n3,4\n5,6\n7,8\n"));
+        assert!(!CsvCodec.matches_format(b"a,b\n1,2\n3,4\n"));
+        assert!(!CsvCodec.matches_format(b"one\ntwo, three\nfour\nfive, six\n"));
+    }
+}
@@ -1,9 +1,15 @@
 use bitvec::{order::Lsb0, vec::BitVec};
 
 use crate::binarytree::BinaryTree;
+use crate::compressor::{selector_width, SELECTOR_SEGMENT_LEN};
 use crate::savebits::SaveBits;
 
 pub fn decompress(data: Vec<u8>) -> Vec<u8> {
+    decompress_symbols(data)
+}
+
+/// Decompresses a sequence of symbols compressed with `compress_symbols`.
+pub fn decompress_symbols<T: SaveBits + Clone>(data: Vec<u8>) -> Vec<T> {
     let bits: BitVec<Lsb0, u8> = BitVec::from_vec(data);
     let mut it = bits.iter().map(|r| *r);
 
@@ -18,19 +24,64 @@ pub fn decompress(data: Vec<u8>) -> Vec<u8> {
         padding += 4;
     }
 
-    let tree = BinaryTree::from_bits(&mut it);
-
-    apply_tree(&mut it.take(bits.elements() * 8 - padding), &tree)
-}
+    // the three bits holding the padding have already been consumed
+    let mut it = it.take(bits.len() - padding - 3);
 
-fn apply_tree(data: &mut impl Iterator<Item = bool>, tree: &BinaryTree<u8>) -> Vec<u8> {
+    let num_blocks = usize::from_bits(&mut it) - 1;
     let mut decompressed = Vec::new();
-
-    while let Some(byte) = tree.get_leaf(data) {
-        decompressed.push(*byte);
+    for _ in 0..num_blocks {
+        let block_len = usize::from_bits(&mut it);
+        decompress_block(&mut it, block_len, &mut decompressed);
     }
 
-    assert_eq!(None, data.next());
+    assert_eq!(None, it.next());
 
     decompressed
 }
+
+/// Decodes a block of `block_len` symbols coded with one or more trees and appends
+/// it to the output.
+fn decompress_block<T: SaveBits + Clone>(
+    data: &mut impl Iterator<Item = bool>,
+    block_len: usize,
+    output: &mut Vec<T>,
+) {
+    let num_trees = usize::from_bits(data);
+    let trees: Vec<BinaryTree<T>> = (0..num_trees)
+        .map(|_| BinaryTree::from_bits(data))
+        .collect();
+
+    let num_segments = block_len.div_ceil(SELECTOR_SEGMENT_LEN);
+    let selector_width = selector_width(num_trees);
+    let selectors: Vec<usize> = (0..num_segments)
+        .map(|_| {
+            (0..selector_width).fold(0, |selector, _| {
+                let bit = data
+                    .next()
+                    .expect("Iterator returned None while loading selectors");
+                selector << 1 | bit as usize
+            })
+        })
+        .collect();
+
+    for (segment_idx, selector) in selectors.into_iter().enumerate() {
+        let segment_len = SELECTOR_SEGMENT_LEN.min(block_len - segment_idx * SELECTOR_SEGMENT_LEN);
+        apply_tree(data, &trees[selector], segment_len, output);
+    }
+}
+
+/// Decodes `num_symbols` symbols from the iterator using the tree and appends them to
+/// the output. Note that a tree consisting of a single leaf does not consume any bits.
+fn apply_tree<T: Clone>(
+    data: &mut impl Iterator<Item = bool>,
+    tree: &BinaryTree<T>,
+    num_symbols: usize,
+    output: &mut Vec<T>,
+) {
+    for _ in 0..num_symbols {
+        let symbol = tree
+            .get_leaf(data)
+            .expect("Iterator returned None while decoding block");
+        output.push(symbol.clone());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::canonical::{decode_canonical, encode_canonical};
+use crate::codec::Codec;
+use crate::stats::DataStats;
+use crate::streams::{join_streams, split_streams};
+
+/// Compresses the data as a sequence of byte pairs with a canonical Huffman code.
+/// This captures correlations between adjacent bytes that coding single bytes misses,
+/// which helps data with small alphabets such as DNA or hex dumps. A trailing byte
+/// of odd-length data is stored as is.
+pub fn compress_digrams(data: &[u8]) -> Vec<u8> {
+    let digrams: Vec<u32> = data
+        .chunks_exact(2)
+        .map(|pair| (pair[0] as u32) << 8 | pair[1] as u32)
+        .collect();
+    let trailing_byte = data.chunks_exact(2).remainder().to_vec();
+
+    join_streams(&[trailing_byte, encode_canonical(&digrams)])
+}
+
+pub fn decompress_digrams(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in digram data");
+
+    let trailing_byte = next_stream();
+    let mut decompressed: Vec<u8> = decode_canonical(&next_stream())
+        .into_iter()
+        .flat_map(|digram| vec![(digram >> 8) as u8, digram as u8])
+        .collect();
+    decompressed.extend(trailing_byte);
+    decompressed
+}
+
+/// Codes pairs of bytes with a canonical code.
+pub struct DigramCodec;
+
+impl Codec for DigramCodec {
+    fn name(&self) -> &'static str {
+        "digram"
+    }
+
+    fn id(&self) -> u8 {
+        4
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_digrams(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_digrams(data)
+    }
+
+    fn estimate_size(&self, stats: &DataStats) -> Option<usize> {
+        // the header stores a delta coded symbol and a code length for every digram
+        let bits = stats.digram_entropy + 12.0 * stats.distinct_digrams as f64;
+        Some((bits / 8.0) as usize + stats.len % 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_decompress_digrams() {
+        let inputs = [
+            Vec::new(),
+            b"A".to_vec(),
+            b"GATTACA".repeat(100),
+            b"0f3a9c00ffe1".repeat(51),
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(&decompress_digrams(&compress_digrams(input)), input);
+        }
+    }
+}
@@ -1,10 +1,15 @@
+use compression::pipeline::PipelineSpecError;
 use std::fmt;
 use std::io;
 
+#[allow(clippy::enum_variant_names)]
 pub enum ProgramError {
     FileReadError(io::Error, std::path::PathBuf),
     FileWriteError(io::Error, std::path::PathBuf),
     InvalidArgumentsError,
+    ConflictingPipelineError,
+    UnknownMethodError(String),
+    UnknownTransformError(String),
 }
 
 impl fmt::Display for ProgramError {
@@ -21,6 +26,18 @@ impl fmt::Display for ProgramError {
                 _ => write!(f, "An unknown error occurred while trying to write to file {}", path.to_string_lossy()),
             },
             ProgramError::InvalidArgumentsError => write!(f, "Invalid arguments; please use [c(ompress) / d(ecompress)] [input_path] [output_path]"),
+            ProgramError::ConflictingPipelineError => write!(f, "Invalid arguments; -p / --pipeline can not be combined with -m / --method or --transform"),
+            ProgramError::UnknownMethodError(name) => write!(f, "Unknown compression method {}", name),
+            ProgramError::UnknownTransformError(name) => write!(f, "Unknown transform {}", name),
+        }
+    }
+}
+
+impl From<PipelineSpecError> for ProgramError {
+    fn from(e: PipelineSpecError) -> Self {
+        match e {
+            PipelineSpecError::UnknownTransform(name) => ProgramError::UnknownTransformError(name),
+            PipelineSpecError::UnknownMethod(name) => ProgramError::UnknownMethodError(name),
         }
     }
 }
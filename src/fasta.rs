@@ -0,0 +1,478 @@
+use crate::codec::Codec;
+use crate::columns::{decode_column, encode_column};
+use crate::huffman::HuffmanCodec;
+use crate::streams::{join_streams, split_streams};
+
+/// The nucleotides that are packed into two bits each.
+const BASES: [u8; 4] = [b'A', b'C', b'G', b'T'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Header lines that start with `>` followed by lines of sequence. Any data can
+    /// be read as FASTA.
+    Fasta = 0,
+    /// Records of a header, a sequence, a `+` line and a quality line of the same
+    /// length as the sequence.
+    Fastq = 1,
+}
+
+/// What the `+` lines of FASTQ records hold after the `+`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlusLine {
+    Empty = 0,
+    /// The `+` line repeats the header of the record.
+    Header = 1,
+    /// The `+` lines are coded as a column of their own.
+    Other = 2,
+}
+
+/// Compresses FASTA and FASTQ sequence files. The headers are coded as a column,
+/// the nucleotides are packed into two bits each with runs of other bytes such as
+/// `N` and lowercase masks stored in side streams, and every quality score is
+/// coded with a tree chosen by the previous score of the read.
+pub fn compress_fasta(data: &[u8]) -> Vec<u8> {
+    let (lines, ends_with_newline) = split_lines(data);
+    let is_fastq = !lines.is_empty()
+        && lines.len() % 4 == 0
+        && lines.chunks(4).all(|record| {
+            record[0].starts_with(b"@")
+                && record[2].starts_with(b"+")
+                && record[1].len() == record[3].len()
+        });
+    let format = if is_fastq {
+        Format::Fastq
+    } else {
+        Format::Fasta
+    };
+
+    let mut streams = vec![vec![format as u8, ends_with_newline as u8]];
+    match format {
+        Format::Fasta => streams.extend(encode_fasta(&lines)),
+        Format::Fastq => streams.extend(encode_fastq(&lines)),
+    }
+    join_streams(&streams)
+}
+
+pub fn decompress_fasta(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let flags = streams.next().expect("Missing stream in FASTA data");
+    let lines = match flags[0] {
+        x if x == Format::Fasta as u8 => decode_fasta(&mut streams),
+        x if x == Format::Fastq as u8 => decode_fastq(&mut streams),
+        _ => panic!("Unknown sequence format"),
+    };
+
+    let mut output = lines.join(&b'\n');
+    if flags[1] == 1 {
+        output.push(b'\n');
+    }
+    output
+}
+
+/// Whether at least a quarter of the lines are made of nucleotides, which is the
+/// case for both FASTA and FASTQ.
+fn is_sequence_data(data: &[u8]) -> bool {
+    let (lines, _) = split_lines(data);
+    let num_sequences = lines
+        .iter()
+        .filter(|line| !line.is_empty() && line.iter().all(|byte| b"ACGTNacgtn".contains(byte)))
+        .count();
+    num_sequences > 0 && 4 * num_sequences >= lines.len()
+}
+
+/// Splits the data into lines. Returns the lines and whether the last line ended in
+/// a line break.
+fn split_lines(data: &[u8]) -> (Vec<&[u8]>, bool) {
+    let ends_with_newline = data.last() == Some(&b'\n');
+    let mut lines: Vec<&[u8]> = data.split(|byte| *byte == b'\n').collect();
+    if data.is_empty() || ends_with_newline {
+        lines.pop();
+    }
+    (lines, ends_with_newline)
+}
+
+fn encode_fasta(lines: &[&[u8]]) -> Vec<Vec<u8>> {
+    let is_header = |line: &&[u8]| line.starts_with(b">");
+    let headers: Vec<&[u8]> = lines
+        .iter()
+        .filter(|line| is_header(line))
+        .map(|line| &line[1..])
+        .collect();
+    // headers are marked with one and sequence lines with their length plus two
+    let line_lens: Vec<usize> = lines
+        .iter()
+        .map(|line| if is_header(line) { 1 } else { line.len() + 2 })
+        .collect();
+    let sequence: Vec<u8> = lines
+        .iter()
+        .filter(|line| !is_header(line))
+        .flat_map(|line| line.iter().copied())
+        .collect();
+
+    vec![
+        HuffmanCodec::<usize>::new().encode(&line_lens),
+        encode_column(&headers),
+        encode_sequence(&sequence),
+    ]
+}
+
+fn decode_fasta(streams: &mut dyn Iterator<Item = Vec<u8>>) -> Vec<Vec<u8>> {
+    let mut next_stream = || streams.next().expect("Missing stream in FASTA data");
+    let line_lens = HuffmanCodec::<usize>::new().decode(next_stream());
+    let num_headers = line_lens.iter().filter(|len| **len == 1).count();
+    let mut headers = decode_column(&next_stream(), num_headers).into_iter();
+    let sequence_len = line_lens
+        .iter()
+        .filter(|len| **len > 1)
+        .map(|len| len - 2)
+        .sum();
+    let sequence = decode_sequence(&next_stream(), sequence_len);
+
+    let mut pos = 0;
+    line_lens
+        .into_iter()
+        .map(|len| match len {
+            1 => [&b">"[..], &headers.next().expect("Missing FASTA header")].concat(),
+            _ => {
+                pos += len - 2;
+                sequence[pos - (len - 2)..pos].to_vec()
+            }
+        })
+        .collect()
+}
+
+fn encode_fastq(lines: &[&[u8]]) -> Vec<Vec<u8>> {
+    let records: Vec<&[&[u8]]> = lines.chunks(4).collect();
+    let headers: Vec<&[u8]> = records.iter().map(|record| &record[0][1..]).collect();
+    let plus_lines: Vec<&[u8]> = records.iter().map(|record| &record[2][1..]).collect();
+    // lengths are offset by one, since zero can not be saved
+    let read_lens: Vec<usize> = records.iter().map(|record| record[1].len() + 1).collect();
+    let sequence: Vec<u8> = records
+        .iter()
+        .flat_map(|record| record[1].iter().copied())
+        .collect();
+    let qualities: Vec<&[u8]> = records.iter().map(|record| record[3]).collect();
+
+    let plus_line = if plus_lines.iter().all(|line| line.is_empty()) {
+        PlusLine::Empty
+    } else if plus_lines.iter().zip(headers.iter()).all(|(a, b)| a == b) {
+        PlusLine::Header
+    } else {
+        PlusLine::Other
+    };
+
+    let mut streams = vec![
+        HuffmanCodec::<usize>::new().encode(&read_lens),
+        encode_column(&headers),
+        vec![plus_line as u8],
+    ];
+    if plus_line == PlusLine::Other {
+        streams.push(encode_column(&plus_lines));
+    }
+    streams.push(encode_sequence(&sequence));
+    streams.extend(encode_qualities(&qualities));
+    streams
+}
+
+fn decode_fastq(streams: &mut dyn Iterator<Item = Vec<u8>>) -> Vec<Vec<u8>> {
+    let mut next_stream = || streams.next().expect("Missing stream in FASTQ data");
+    let read_lens: Vec<usize> = HuffmanCodec::<usize>::new()
+        .decode(next_stream())
+        .into_iter()
+        .map(|len| len - 1)
+        .collect();
+    let num_records = read_lens.len();
+    let headers = decode_column(&next_stream(), num_records);
+    let plus_lines = match next_stream()[0] {
+        x if x == PlusLine::Empty as u8 => vec![Vec::new(); num_records],
+        x if x == PlusLine::Header as u8 => headers.clone(),
+        _ => decode_column(&next_stream(), num_records),
+    };
+    let sequence = decode_sequence(&next_stream(), read_lens.iter().sum());
+    let qualities = decode_qualities(streams, &read_lens);
+
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    for (((header, plus_line), quality), len) in headers
+        .into_iter()
+        .zip(plus_lines)
+        .zip(qualities)
+        .zip(read_lens)
+    {
+        lines.push([&b"@"[..], &header].concat());
+        lines.push(sequence[pos..pos + len].to_vec());
+        lines.push([&b"+"[..], &plus_line].concat());
+        lines.push(quality);
+        pos += len;
+    }
+    lines
+}
+
+/// Codes the sequence as runs of lowercase and uppercase letters, runs of bytes
+/// other than nucleotides with the number of nucleotides between them, and the
+/// nucleotides packed four to a byte.
+fn encode_sequence(sequence: &[u8]) -> Vec<u8> {
+    // the runs alternate between uppercase and lowercase, starting with uppercase
+    let mut case_runs = Vec::new();
+    let mut is_lowercase = false;
+    let mut run_len = 0;
+    for byte in sequence {
+        if byte.is_ascii_lowercase() != is_lowercase {
+            case_runs.push(run_len + 1);
+            is_lowercase = !is_lowercase;
+            run_len = 0;
+        }
+        run_len += 1;
+    }
+    case_runs.push(run_len + 1);
+
+    let mut gaps = Vec::new();
+    let mut run_lens = Vec::new();
+    let mut run_bytes = Vec::new();
+    let mut bases = Vec::new();
+    let mut gap = 0;
+    let mut pos = 0;
+    while pos < sequence.len() {
+        let byte = sequence[pos].to_ascii_uppercase();
+        if let Some(base) = BASES.iter().position(|base| *base == byte) {
+            bases.push(base as u8);
+            gap += 1;
+            pos += 1;
+        } else {
+            let run_len = sequence[pos..]
+                .iter()
+                .take_while(|other| other.to_ascii_uppercase() == byte)
+                .count();
+            gaps.push(gap + 1);
+            run_lens.push(run_len);
+            run_bytes.push(byte);
+            gap = 0;
+            pos += run_len;
+        }
+    }
+    let packed: Vec<u8> = bases
+        .chunks(4)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |packed, (x, base)| packed | base << (6 - 2 * x))
+        })
+        .collect();
+
+    let usize_codec = HuffmanCodec::<usize>::new();
+    join_streams(&[
+        usize_codec.encode(&case_runs),
+        usize_codec.encode(&gaps),
+        usize_codec.encode(&run_lens),
+        HuffmanCodec::<u8>::new().encode(&run_bytes),
+        packed,
+    ])
+}
+
+fn decode_sequence(data: &[u8], len: usize) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in sequence");
+    let usize_codec = HuffmanCodec::<usize>::new();
+    let case_runs = usize_codec.decode(next_stream());
+    let gaps = usize_codec.decode(next_stream());
+    let run_lens = usize_codec.decode(next_stream());
+    let run_bytes = HuffmanCodec::<u8>::new().decode(next_stream());
+    let packed = next_stream();
+
+    let mut bases = packed
+        .iter()
+        .flat_map(|packed| (0..4).map(move |x| BASES[(packed >> (6 - 2 * x) & 3) as usize]));
+    let mut sequence = Vec::with_capacity(len);
+    for ((gap, run_len), byte) in gaps.into_iter().zip(run_lens).zip(run_bytes) {
+        sequence.extend(bases.by_ref().take(gap - 1));
+        sequence.extend(std::iter::repeat_n(byte, run_len));
+    }
+    let num_remaining = len - sequence.len();
+    sequence.extend(bases.take(num_remaining));
+
+    let mut pos = 0;
+    for (x, run_len) in case_runs.into_iter().enumerate() {
+        let run = &mut sequence[pos..pos + run_len - 1];
+        if x % 2 == 1 {
+            run.make_ascii_lowercase();
+        }
+        pos += run_len - 1;
+    }
+    sequence
+}
+
+/// Codes the quality scores with a tree for every previous score of the read. The
+/// first score of a read uses the context of zero.
+fn encode_qualities(qualities: &[&[u8]]) -> Vec<Vec<u8>> {
+    let mut groups = vec![Vec::new(); 256];
+    for quality in qualities {
+        let mut context = 0;
+        for score in quality.iter() {
+            groups[context].push(*score);
+            context = *score as usize;
+        }
+    }
+    while groups.last().is_some_and(Vec::is_empty) {
+        groups.pop();
+    }
+    groups
+        .iter()
+        .map(|group| {
+            if group.is_empty() {
+                Vec::new()
+            } else {
+                HuffmanCodec::<u8>::new().encode(group)
+            }
+        })
+        .collect()
+}
+
+fn decode_qualities(
+    streams: &mut dyn Iterator<Item = Vec<u8>>,
+    read_lens: &[usize],
+) -> Vec<Vec<u8>> {
+    let mut groups: Vec<_> = streams
+        .map(|stream| match stream.is_empty() {
+            true => Vec::new().into_iter(),
+            false => HuffmanCodec::<u8>::new().decode(stream).into_iter(),
+        })
+        .collect();
+    groups.resize_with(256, || Vec::new().into_iter());
+
+    read_lens
+        .iter()
+        .map(|len| {
+            let mut context = 0;
+            (0..*len)
+                .map(|_| {
+                    let score = groups[context]
+                        .next()
+                        .expect("Iterator returned None while loading quality scores");
+                    context = score as usize;
+                    score
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Codes FASTA and FASTQ files with packed nucleotides.
+pub struct FastaCodec;
+
+impl Codec for FastaCodec {
+    fn name(&self) -> &'static str {
+        "fasta"
+    }
+
+    fn id(&self) -> u8 {
+        11
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_fasta(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_fasta(data)
+    }
+
+    fn matches_format(&self, data: &[u8]) -> bool {
+        is_sequence_data(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::compress;
+
+    /// A pseudorandom sequence with a lowercase repeat and a run of `N`.
+    fn genome(len: usize) -> Vec<u8> {
+        let mut state: u32 = 12345;
+        (0..len)
+            .map(|x| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let base = BASES[(state >> 16) as usize % 4];
+                match x % 1000 {
+                    0..=99 => b'N',
+                    500..=599 => base.to_ascii_lowercase(),
+                    _ => base,
+                }
+            })
+            .collect()
+    }
+
+    fn fasta() -> Vec<u8> {
+        let mut fasta = Vec::new();
+        for (x, chromosome) in genome(20_000).chunks(7000).enumerate() {
+            fasta.extend(format!(">chr{} assembled\n", x + 1).bytes());
+            for line in chromosome.chunks(60) {
+                fasta.extend_from_slice(line);
+                fasta.push(b'\n');
+            }
+        }
+        fasta
+    }
+
+    fn fastq() -> Vec<u8> {
+        let mut fastq = Vec::new();
+        for (x, read) in genome(15_000).chunks(100).enumerate() {
+            let quality: Vec<u8> = (0..read.len())
+                .map(|y| b"?????>>;;5#"[(y + x) % 97 / 9])
+                .collect();
+            fastq.extend(format!("@SRR001.{} length={}\n", x + 1, read.len()).bytes());
+            fastq.extend_from_slice(read);
+            fastq.extend_from_slice(b"\n+\n");
+            fastq.extend(quality);
+            fastq.push(b'\n');
+        }
+        fastq
+    }
+
+    #[test]
+    fn test_encode_decode_sequence() {
+        let sequences: [&[u8]; 5] = [b"", b"ACGT", b"acgtNNNNnnACGTRYacGT", b"NNNN", b"GATTACA\r"];
+        for sequence in sequences.iter() {
+            let decoded = decode_sequence(&encode_sequence(sequence), sequence.len());
+            assert_eq!(&decoded, sequence);
+        }
+    }
+
+    #[test]
+    fn test_compress_decompress_fasta() {
+        let fasta = fasta();
+        let fastq = fastq();
+        let plus_header = String::from_utf8(fastq.clone())
+            .unwrap()
+            .replace("\n+\n", "\n+SRR\n");
+        let inputs = [
+            b"".to_vec(),
+            b"\n".to_vec(),
+            b">only header".to_vec(),
+            b"ACGT\n>x\n\nacgtn".to_vec(),
+            b"@r1\nACGT\n+r1\nIIII\n@r2\nAC\n+r2\n##".to_vec(),
+            b"@r1\nACGT\n+\nIII\n".to_vec(),
+            fasta.clone(),
+            fastq.clone(),
+            plus_header.into_bytes(),
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&decompress_fasta(&compress_fasta(input)), input);
+        }
+
+        // two bits per nucleotide, with the N runs and line breaks almost free
+        assert!(compress_fasta(&fasta).len() < fasta.len() / 4);
+        assert!(compress_fasta(&fastq).len() < compress(&fastq).len() * 3 / 5);
+    }
+
+    #[test]
+    fn test_matches_format() {
+        let fasta = fasta();
+        let fastq = fastq();
+        assert!(FastaCodec.matches_format(&fasta));
+        assert!(FastaCodec.matches_format(&fastq[1000..]));
+        assert!(!FastaCodec.matches_format(b"a cat\nand a dog\n"));
+        assert!(!FastaCodec.matches_format(b""));
+    }
+}
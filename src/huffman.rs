@@ -0,0 +1,123 @@
+use crate::codec::Codec;
+use crate::compressor::compress_symbols;
+use crate::decompressor::decompress_symbols;
+use crate::savebits::SaveBits;
+use crate::stats::DataStats;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Huffman coder for sequences of arbitrary symbols. The symbols are stored in
+/// the trees of the compressed data using their `SaveBits` implementation, so
+/// anything from `u16` samples to enum tokens can be coded directly.
+pub struct HuffmanCodec<T> {
+    symbol: PhantomData<T>,
+}
+
+impl<T: SaveBits + Hash + Ord + Clone> HuffmanCodec<T> {
+    pub fn new() -> HuffmanCodec<T> {
+        HuffmanCodec {
+            symbol: PhantomData,
+        }
+    }
+
+    pub fn encode(&self, symbols: &[T]) -> Vec<u8> {
+        compress_symbols(symbols)
+    }
+
+    pub fn decode(&self, data: Vec<u8>) -> Vec<T> {
+        decompress_symbols(data)
+    }
+}
+
+impl<T: SaveBits + Hash + Ord + Clone> Default for HuffmanCodec<T> {
+    fn default() -> Self {
+        HuffmanCodec::new()
+    }
+}
+
+/// Huffman coding of bytes.
+impl Codec for HuffmanCodec<u8> {
+    fn name(&self) -> &'static str {
+        "huffman"
+    }
+
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_symbols(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_symbols(data.to_vec())
+    }
+
+    fn estimate_size(&self, stats: &DataStats) -> Option<usize> {
+        // every symbol takes about ten bits in the tree
+        Some(((stats.byte_entropy + 10.0 * stats.distinct_bytes as f64) / 8.0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+    enum Token {
+        Word(u16),
+        Space,
+        Newline,
+    }
+
+    impl SaveBits for Token {
+        fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+            match self {
+                Token::Word(x) => Box::new(std::iter::once(true).chain(x.save_bits())),
+                Token::Space => Box::new([false, false].iter().copied()),
+                Token::Newline => Box::new([false, true].iter().copied()),
+            }
+        }
+
+        fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+            match iter.next() {
+                Some(true) => Token::Word(u16::from_bits(iter)),
+                Some(false) if iter.next() == Some(true) => Token::Newline,
+                Some(false) => Token::Space,
+                None => panic!("Iterator returned None while loading Token"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_u16_symbols() {
+        let codec = HuffmanCodec::<u16>::new();
+        let inputs = [
+            vec![],
+            vec![1000u16],
+            vec![0, 65535, 0, 65535, 300, 300, 300],
+            (0..5000u16).map(|x| x % 70 * 900).collect(),
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(&codec.decode(codec.encode(input)), input);
+        }
+    }
+
+    #[test]
+    fn test_enum_symbols() {
+        let codec = HuffmanCodec::default();
+        let input: Vec<Token> = (0..1000u16)
+            .flat_map(|x| {
+                let separator = if x % 7 == 0 {
+                    Token::Newline
+                } else {
+                    Token::Space
+                };
+                vec![Token::Word(x % 13 * 5000), separator]
+            })
+            .collect();
+
+        assert_eq!(codec.decode(codec.encode(&input)), input);
+    }
+}
@@ -0,0 +1,386 @@
+use bitvec::{order::Lsb0, vec::BitVec};
+
+use crate::savebits::{bit_width, load_bits_with_width, save_bits_with_width};
+use crate::varint::{read_varint, write_varint};
+
+/// Number of values in a block of the block-based codecs. Every block is packed
+/// with its own bit width and starts at a byte boundary, so blocks can be unpacked
+/// independently.
+pub const INT_BLOCK_LEN: usize = 128;
+
+/// A codec for arrays of integers, such as sorted id lists and posting lists.
+/// Signed integers can be coded by wrapping a codec in `ZigZag`.
+pub trait IntCodec {
+    fn name(&self) -> &'static str;
+    fn encode(&self, values: &[u64]) -> Vec<u8>;
+    fn decode(&self, data: &[u8]) -> Vec<u64>;
+}
+
+/// Returns all of the integer codecs.
+pub fn int_codecs() -> Vec<Box<dyn IntCodec>> {
+    vec![
+        Box::new(Varint),
+        Box::new(ZigZag(Varint)),
+        Box::new(FrameOfReference),
+        Box::new(BitPacking),
+        Box::new(PForDelta),
+    ]
+}
+
+/// Saves every value as a LEB128 varint: seven bits at a time, starting from the
+/// least significant bits, with the highest bit of a byte telling whether more
+/// bytes follow.
+pub struct Varint;
+
+impl IntCodec for Varint {
+    fn name(&self) -> &'static str {
+        "varint"
+    }
+
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let mut output = Vec::new();
+        for value in values {
+            write_varint(*value, &mut output);
+        }
+        output
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u64> {
+        let mut values = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            values.push(read_varint(data, &mut pos));
+        }
+        values
+    }
+}
+
+/// Maps the values from two's complement to zigzag order before coding them with
+/// the inner codec, so that signed integers close to zero become small numbers:
+/// 0, -1, 1, -2, 2, ... are mapped to 0, 1, 2, 3, 4, ...
+pub struct ZigZag<C: IntCodec>(pub C);
+
+impl<C: IntCodec> IntCodec for ZigZag<C> {
+    fn name(&self) -> &'static str {
+        "zigzag"
+    }
+
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let mapped: Vec<u64> = values
+            .iter()
+            .map(|value| zigzag_encode(*value as i64))
+            .collect();
+        self.0.encode(&mapped)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u64> {
+        self.0
+            .decode(data)
+            .into_iter()
+            .map(|value| zigzag_decode(value) as u64)
+            .collect()
+    }
+}
+
+pub fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Saves every block as its smallest value followed by the differences of the
+/// values to it, packed with the bit width of the largest difference.
+pub struct FrameOfReference;
+
+impl IntCodec for FrameOfReference {
+    fn name(&self) -> &'static str {
+        "for"
+    }
+
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let mut output = Vec::new();
+        write_varint(values.len() as u64, &mut output);
+        for block in values.chunks(INT_BLOCK_LEN) {
+            let min = *block.iter().min().unwrap();
+            let offsets: Vec<u64> = block.iter().map(|value| value - min).collect();
+            let width = max_bit_width(&offsets);
+            write_varint(min, &mut output);
+            output.push(width as u8);
+            output.extend(pack(&offsets, width));
+        }
+        output
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u64> {
+        let mut pos = 0;
+        let len = read_varint(data, &mut pos) as usize;
+        let mut values = Vec::with_capacity(len);
+        while values.len() < len {
+            let block_len = (len - values.len()).min(INT_BLOCK_LEN);
+            let min = read_varint(data, &mut pos);
+            let width = read_width(data, &mut pos);
+            let offsets = unpack(data, &mut pos, block_len, width);
+            values.extend(offsets.into_iter().map(|offset| min + offset));
+        }
+        values
+    }
+}
+
+/// Packs every block with the bit width of its largest value.
+pub struct BitPacking;
+
+impl IntCodec for BitPacking {
+    fn name(&self) -> &'static str {
+        "bitpack"
+    }
+
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let mut output = Vec::new();
+        write_varint(values.len() as u64, &mut output);
+        for block in values.chunks(INT_BLOCK_LEN) {
+            let width = max_bit_width(block);
+            output.push(width as u8);
+            output.extend(pack(block, width));
+        }
+        output
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u64> {
+        let mut pos = 0;
+        let len = read_varint(data, &mut pos) as usize;
+        let mut values = Vec::with_capacity(len);
+        while values.len() < len {
+            let block_len = (len - values.len()).min(INT_BLOCK_LEN);
+            let width = read_width(data, &mut pos);
+            values.extend(unpack(data, &mut pos, block_len, width));
+        }
+        values
+    }
+}
+
+/// Codes the differences between consecutive values, which are small for sorted
+/// lists. Every block is packed with the bit width that gives the smallest output,
+/// and the few differences that do not fit are saved separately as exceptions:
+/// the position in the block and the bits that did not fit.
+pub struct PForDelta;
+
+impl IntCodec for PForDelta {
+    fn name(&self) -> &'static str {
+        "pfordelta"
+    }
+
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let mut previous = 0u64;
+        let deltas: Vec<u64> = values
+            .iter()
+            .map(|value| {
+                let delta = value.wrapping_sub(previous);
+                previous = *value;
+                delta
+            })
+            .collect();
+
+        let mut output = Vec::new();
+        write_varint(values.len() as u64, &mut output);
+        for block in deltas.chunks(INT_BLOCK_LEN) {
+            let width = (0..=64)
+                .min_by_key(|width| pfor_cost(block, *width))
+                .unwrap();
+            let exceptions: Vec<(usize, u64)> = block
+                .iter()
+                .enumerate()
+                .filter(|(_, delta)| bit_width(**delta) > width)
+                .map(|(x, delta)| (x, delta >> width))
+                .collect();
+
+            output.push(width as u8);
+            output.push(exceptions.len() as u8);
+            output.extend(pack(block, width));
+            for (x, high_bits) in exceptions {
+                output.push(x as u8);
+                write_varint(high_bits, &mut output);
+            }
+        }
+        output
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u64> {
+        let mut pos = 0;
+        let len = read_varint(data, &mut pos) as usize;
+        let mut values = Vec::with_capacity(len);
+        let mut previous = 0u64;
+        while values.len() < len {
+            let block_len = (len - values.len()).min(INT_BLOCK_LEN);
+            let width = read_width(data, &mut pos);
+            let num_exceptions = read_byte(data, &mut pos);
+            let mut deltas = unpack(data, &mut pos, block_len, width);
+            for _ in 0..num_exceptions {
+                let x = read_byte(data, &mut pos) as usize;
+                let high_bits = read_varint(data, &mut pos);
+                deltas[x] |= high_bits << width;
+            }
+
+            for delta in deltas {
+                previous = previous.wrapping_add(delta);
+                values.push(previous);
+            }
+        }
+        values
+    }
+}
+
+/// Number of bytes needed for a block when it is packed with the given width.
+fn pfor_cost(block: &[u64], width: u32) -> usize {
+    let exceptions: usize = block
+        .iter()
+        .filter(|delta| bit_width(**delta) > width)
+        .map(|delta| 1 + varint_len(delta >> width))
+        .sum();
+    (block.len() * width as usize).div_ceil(8) + exceptions
+}
+
+fn max_bit_width(values: &[u64]) -> u32 {
+    values
+        .iter()
+        .map(|value| bit_width(*value))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Packs the lowest `width` bits of every value, padding the end to a whole byte.
+fn pack(values: &[u64], width: u32) -> Vec<u8> {
+    let mut output: BitVec<Lsb0, u8> = BitVec::new();
+    for value in values {
+        output.extend(save_bits_with_width(*value, width));
+    }
+    output.into_vec()
+}
+
+fn unpack(data: &[u8], pos: &mut usize, len: usize, width: u32) -> Vec<u64> {
+    let num_bytes = (len * width as usize).div_ceil(8);
+    let packed = data
+        .get(*pos..*pos + num_bytes)
+        .expect("Data ended while reading packed values");
+    *pos += num_bytes;
+
+    let bits: BitVec<Lsb0, u8> = BitVec::from_vec(packed.to_vec());
+    let mut it = bits.iter().map(|r| *r);
+    (0..len)
+        .map(|_| load_bits_with_width(&mut it, width))
+        .collect()
+}
+
+fn read_byte(data: &[u8], pos: &mut usize) -> u8 {
+    let byte = *data.get(*pos).expect("Data ended while reading a block");
+    *pos += 1;
+    byte
+}
+
+fn read_width(data: &[u8], pos: &mut usize) -> u32 {
+    let width = read_byte(data, pos) as u32;
+    assert!(width <= u64::BITS, "Invalid bit width");
+    width
+}
+
+fn varint_len(value: u64) -> usize {
+    (bit_width(value) as usize).div_ceil(7).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_ids() -> Vec<u64> {
+        let mut state = 12345u32;
+        let mut id = 1000u64;
+        (0..1000)
+            .map(|x| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                id += (state >> 16) as u64 % 20 + 1;
+                if x % 300 == 299 {
+                    id += 100_000;
+                }
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_encode_decode_int_codecs() {
+        let inputs = [
+            vec![],
+            vec![0],
+            vec![u64::MAX, 0, 1, u64::MAX],
+            (0..300).collect(),
+            sorted_ids(),
+        ];
+        for codec in int_codecs() {
+            for input in inputs.iter() {
+                assert_eq!(
+                    &codec.decode(&codec.encode(input)),
+                    input,
+                    "{}",
+                    codec.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_varint() {
+        assert_eq!(
+            Varint.encode(&[1, 127, 128, 300]),
+            [1, 127, 0x80, 1, 0xac, 2]
+        );
+        assert_eq!(varint_len(0), 1);
+        assert_eq!(varint_len(300), 2);
+        assert_eq!(varint_len(u64::MAX), 10);
+    }
+
+    #[test]
+    fn test_zigzag() {
+        let values = [0i64, -1, 1, -2, 2, i64::MIN, i64::MAX];
+        let mapped: Vec<u64> = values.iter().map(|x| zigzag_encode(*x)).collect();
+        assert_eq!(mapped[..5], [0, 1, 2, 3, 4]);
+        assert_eq!(mapped[5..], [u64::MAX, u64::MAX - 1]);
+        for value in values.iter() {
+            assert_eq!(zigzag_decode(zigzag_encode(*value)), *value);
+        }
+
+        let signed: Vec<u64> = [-5i64, 3, -100, 64].iter().map(|x| *x as u64).collect();
+        assert_eq!(ZigZag(Varint).encode(&signed), [9, 6, 199, 1, 128, 1]);
+    }
+
+    #[test]
+    fn test_pack() {
+        // 001 010 011 111, filling every byte from its lowest bit
+        assert_eq!(pack(&[1, 2, 3, 0xff], 3), [0b1001_0100, 0b1111]);
+        assert!(pack(&[0; 10], 0).is_empty());
+
+        for width in [1, 7, 13, 63, 64] {
+            let values: Vec<u64> = (0..INT_BLOCK_LEN as u64)
+                .map(|x| x.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - width))
+                .collect();
+            let packed = pack(&values, width);
+            assert_eq!(packed.len(), (values.len() * width as usize).div_ceil(8));
+            assert_eq!(unpack(&packed, &mut 0, values.len(), width), values);
+        }
+    }
+
+    #[test]
+    fn test_block_codecs_on_sorted_ids() {
+        let ids = sorted_ids();
+        let raw_len = ids.len() * 8;
+
+        let frame = FrameOfReference.encode(&ids).len();
+        let packed = BitPacking.encode(&ids).len();
+        let pfor = PForDelta.encode(&ids).len();
+        assert!(packed < raw_len);
+        assert!(frame < packed);
+        // the large gaps are stored as exceptions
+        assert!(pfor < frame / 2);
+        assert!(pfor < Varint.encode(&ids).len() / 2);
+    }
+}
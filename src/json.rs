@@ -0,0 +1,508 @@
+use crate::codec::Codec;
+use crate::columns::{decode_column, encode_column};
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::huffman::HuffmanCodec;
+use crate::streams::{join_streams, split_streams};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The kind of a token in JSON data. Only keys, strings, numbers, whitespace and
+/// other bytes have contents, the rest are fully determined by their kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Colon,
+    Comma,
+    Key,
+    String,
+    Number,
+    True,
+    False,
+    Null,
+    Whitespace,
+    /// A byte that is not valid JSON, which is kept as it is.
+    Other,
+}
+
+const KINDS: [Kind; 14] = [
+    Kind::ObjectStart,
+    Kind::ObjectEnd,
+    Kind::ArrayStart,
+    Kind::ArrayEnd,
+    Kind::Colon,
+    Kind::Comma,
+    Kind::Key,
+    Kind::String,
+    Kind::Number,
+    Kind::True,
+    Kind::False,
+    Kind::Null,
+    Kind::Whitespace,
+    Kind::Other,
+];
+
+/// Bytes of the tokens that do not have contents.
+const FIXED_TOKENS: [(Kind, &[u8]); 9] = [
+    (Kind::ObjectStart, b"{"),
+    (Kind::ObjectEnd, b"}"),
+    (Kind::ArrayStart, b"["),
+    (Kind::ArrayEnd, b"]"),
+    (Kind::Colon, b":"),
+    (Kind::Comma, b","),
+    (Kind::True, b"true"),
+    (Kind::False, b"false"),
+    (Kind::Null, b"null"),
+];
+
+struct Token<'a> {
+    kind: Kind,
+    /// The contents of the token. Strings and keys do not include their quotes.
+    contents: &'a [u8],
+}
+
+/// Compresses JSON or newline-delimited JSON by separating the structure of the data
+/// from its values. The kinds of the tokens are predicted from the two previous kinds
+/// and the current key, and the keys from the previous key, which is almost always
+/// right for objects that repeat the same keys. Strings and numbers are coded as
+/// columns by the key they belong to, so that the values of a key are coded together
+/// with trees of their own. Strings are kept with their escapes, so the data is
+/// reproduced exactly, even if it is not valid JSON.
+pub fn compress_json(data: &[u8]) -> Vec<u8> {
+    let mut kinds = PredictedSymbols::new();
+    let mut keys = PredictedSymbols::new();
+    let mut dictionary = Vec::new();
+    let mut indices = HashMap::new();
+    let mut strings: Vec<Vec<&[u8]>> = Vec::new();
+    let mut numbers: Vec<Vec<&[u8]>> = Vec::new();
+    let mut whitespace = Vec::new();
+    let mut other = Vec::new();
+
+    let mut previous_kinds = (None, None);
+    let mut key = None;
+    for token in tokenize(data) {
+        kinds.push((previous_kinds, key), token.kind as usize);
+        previous_kinds = (previous_kinds.1, Some(token.kind as usize));
+        match token.kind {
+            Kind::Key => {
+                let idx = *indices.entry(token.contents).or_insert_with(|| {
+                    dictionary.push(token.contents);
+                    dictionary.len() - 1
+                });
+                keys.push(key, idx);
+                key = Some(idx);
+            }
+            Kind::String => value_group(&mut strings, key).push(token.contents),
+            Kind::Number => value_group(&mut numbers, key).push(token.contents),
+            Kind::Whitespace => whitespace.push(token.contents),
+            Kind::Other => other.extend_from_slice(token.contents),
+            _ => {}
+        }
+    }
+
+    let mut streams = kinds.encode();
+    streams.extend(keys.encode());
+    streams.push(encode_column(&dictionary));
+    for group in strings.iter().chain(numbers.iter()) {
+        if !group.is_empty() {
+            streams.push(encode_column(group));
+        }
+    }
+    streams.push(encode_column(&whitespace));
+    streams.push(compress(&other));
+    join_streams(&streams)
+}
+
+/// Whether the data starts with an object or an array. The first whole line is also
+/// checked, since a block of newline-delimited JSON may start in the middle of a line.
+fn is_json(data: &[u8]) -> bool {
+    let starts_with_value = |data: &[u8]| {
+        matches!(
+            data.iter().find(|byte| !byte.is_ascii_whitespace()),
+            Some(b'{') | Some(b'[')
+        )
+    };
+    starts_with_value(data)
+        || data
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .is_some_and(|x| starts_with_value(&data[x + 1..]))
+}
+
+pub fn decompress_json(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in JSON data");
+
+    let mut kind_decoder = PredictedSymbolsDecoder::new(next_stream(), next_stream());
+    let mut key_decoder = PredictedSymbolsDecoder::new(next_stream(), next_stream());
+    let mut kinds = Vec::new();
+    let mut key_indices = Vec::new();
+    let mut num_strings = Vec::new();
+    let mut num_numbers = Vec::new();
+    let mut previous_kinds = (None, None);
+    let mut key = None;
+    while !kind_decoder.is_finished() {
+        let kind_idx = kind_decoder.next((previous_kinds, key));
+        let kind = *KINDS.get(kind_idx).expect("Unknown JSON token");
+        previous_kinds = (previous_kinds.1, Some(kind_idx));
+        match kind {
+            Kind::Key => {
+                let idx = key_decoder.next(key);
+                key_indices.push(idx);
+                key = Some(idx);
+            }
+            Kind::String => *value_group(&mut num_strings, key) += 1,
+            Kind::Number => *value_group(&mut num_numbers, key) += 1,
+            _ => {}
+        }
+        kinds.push(kind);
+    }
+
+    let num_keys = key_indices.iter().max().map_or(0, |idx| idx + 1);
+    let dictionary = decode_column(&next_stream(), num_keys);
+    let mut decode_groups = |counts: &[usize]| -> Vec<_> {
+        counts
+            .iter()
+            .map(|len| match len {
+                0 => Vec::new().into_iter(),
+                _ => decode_column(&next_stream(), *len).into_iter(),
+            })
+            .collect()
+    };
+    let mut strings = decode_groups(&num_strings);
+    let mut numbers = decode_groups(&num_numbers);
+    let num_whitespace = kinds
+        .iter()
+        .filter(|kind| **kind == Kind::Whitespace)
+        .count();
+    let mut whitespace = decode_column(&next_stream(), num_whitespace).into_iter();
+    let mut other = decompress(next_stream()).into_iter();
+
+    let missing = "JSON data has too few values";
+    let mut key_indices = key_indices.into_iter();
+    let mut key = None;
+    let mut output = Vec::new();
+    for kind in kinds {
+        match kind {
+            Kind::Key => {
+                let idx = key_indices.next().unwrap();
+                output.push(b'"');
+                output.extend_from_slice(&dictionary[idx]);
+                output.push(b'"');
+                key = Some(idx);
+            }
+            Kind::String => {
+                output.push(b'"');
+                output.extend(value_group(&mut strings, key).next().expect(missing));
+                output.push(b'"');
+            }
+            Kind::Number => output.extend(value_group(&mut numbers, key).next().expect(missing)),
+            Kind::Whitespace => output.extend(whitespace.next().expect(missing)),
+            Kind::Other => output.push(other.next().expect(missing)),
+            _ => {
+                let (_, bytes) = FIXED_TOKENS.iter().find(|(x, _)| *x == kind).unwrap();
+                output.extend_from_slice(bytes);
+            }
+        }
+    }
+    output
+}
+
+/// Returns the group of the values that belong to the key. Values before the first
+/// key have a group of their own.
+fn value_group<T: Default>(groups: &mut Vec<T>, key: Option<usize>) -> &mut T {
+    let idx = key.map_or(0, |idx| idx + 1);
+    if groups.len() <= idx {
+        groups.resize_with(idx + 1, T::default);
+    }
+    &mut groups[idx]
+}
+
+/// Splits the data into tokens. A string is a key if the next token after any
+/// whitespace is a colon.
+fn tokenize(data: &[u8]) -> Vec<Token<'_>> {
+    let is_whitespace = |byte: &u8| b" \t\n\r".contains(byte);
+    let is_number_byte = |byte: &u8| byte.is_ascii_digit() || b"+-.eE".contains(byte);
+
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let rest = &data[pos..];
+        let fixed = FIXED_TOKENS
+            .iter()
+            .find(|(_, bytes)| rest.starts_with(bytes));
+
+        let (kind, len) = if let Some((kind, bytes)) = fixed {
+            (*kind, bytes.len())
+        } else if is_whitespace(&rest[0]) {
+            (
+                Kind::Whitespace,
+                rest.iter().take_while(|x| is_whitespace(x)).count(),
+            )
+        } else if rest[0] == b'-' || rest[0].is_ascii_digit() {
+            (
+                Kind::Number,
+                rest.iter().take_while(|x| is_number_byte(x)).count(),
+            )
+        } else if rest[0] == b'"' {
+            match string_len(rest) {
+                Some(len) => (Kind::String, len),
+                None => (Kind::Other, 1),
+            }
+        } else {
+            (Kind::Other, 1)
+        };
+
+        let contents = match kind {
+            Kind::String => &rest[1..len - 1],
+            _ => &rest[..len],
+        };
+        tokens.push(Token { kind, contents });
+        pos += len;
+    }
+
+    for x in 0..tokens.len() {
+        if tokens[x].kind == Kind::String {
+            let next = tokens[x + 1..]
+                .iter()
+                .find(|token| token.kind != Kind::Whitespace);
+            if next.map(|token| token.kind) == Some(Kind::Colon) {
+                tokens[x].kind = Kind::Key;
+            }
+        }
+    }
+    tokens
+}
+
+/// Returns the length of the string at the start of the data, including the quotes,
+/// or `None` if the string is not closed.
+fn string_len(data: &[u8]) -> Option<usize> {
+    let mut pos = 1;
+    while pos < data.len() {
+        match data[pos] {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+/// Codes symbols that are predicted to be the same as the last symbol that was seen
+/// in the same context. Runs of correct predictions are coded as their lengths, and
+/// every run is followed by the symbol that was not predicted correctly.
+struct PredictedSymbols<C: Hash + Eq> {
+    predictions: HashMap<C, usize>,
+    run_lens: Vec<usize>,
+    misses: Vec<usize>,
+    run_len: usize,
+}
+
+impl<C: Hash + Eq> PredictedSymbols<C> {
+    fn new() -> PredictedSymbols<C> {
+        PredictedSymbols {
+            predictions: HashMap::new(),
+            run_lens: Vec::new(),
+            misses: Vec::new(),
+            run_len: 0,
+        }
+    }
+
+    fn push(&mut self, context: C, symbol: usize) {
+        if self.predictions.insert(context, symbol) == Some(symbol) {
+            self.run_len += 1;
+        } else {
+            self.run_lens.push(self.run_len);
+            self.misses.push(symbol);
+            self.run_len = 0;
+        }
+    }
+
+    fn encode(mut self) -> Vec<Vec<u8>> {
+        self.run_lens.push(self.run_len);
+        // values are offset by one, since zero can not be saved
+        let offset = |values: Vec<usize>| -> Vec<usize> { values.iter().map(|x| x + 1).collect() };
+        let codec = HuffmanCodec::new();
+        vec![
+            codec.encode(&offset(self.run_lens)),
+            codec.encode(&offset(self.misses)),
+        ]
+    }
+}
+
+struct PredictedSymbolsDecoder<C: Hash + Eq> {
+    predictions: HashMap<C, usize>,
+    run_lens: std::vec::IntoIter<usize>,
+    misses: std::vec::IntoIter<usize>,
+    run_len: usize,
+}
+
+impl<C: Hash + Eq> PredictedSymbolsDecoder<C> {
+    fn new(run_lens: Vec<u8>, misses: Vec<u8>) -> PredictedSymbolsDecoder<C> {
+        let codec = HuffmanCodec::<usize>::new();
+        let decode = |data| -> Vec<usize> { codec.decode(data).iter().map(|x| x - 1).collect() };
+        let mut run_lens = decode(run_lens).into_iter();
+        let run_len = run_lens.next().unwrap_or(0);
+        PredictedSymbolsDecoder {
+            predictions: HashMap::new(),
+            run_lens,
+            misses: decode(misses).into_iter(),
+            run_len,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.run_len == 0 && self.misses.len() == 0
+    }
+
+    fn next(&mut self, context: C) -> usize {
+        let symbol = if self.run_len > 0 {
+            self.run_len -= 1;
+            *self
+                .predictions
+                .get(&context)
+                .expect("Predicted a symbol in a new context")
+        } else {
+            self.run_len = self.run_lens.next().expect("Missing run of predictions");
+            self.misses.next().expect("Missing symbol")
+        };
+        self.predictions.insert(context, symbol);
+        symbol
+    }
+}
+
+/// Codes JSON and newline-delimited JSON.
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn id(&self) -> u8 {
+        9
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_json(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_json(data)
+    }
+
+    fn matches_format(&self, data: &[u8]) -> bool {
+        is_json(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_log() -> Vec<u8> {
+        (0..300)
+            .flat_map(|x| {
+                format!(
+                    "{{\"time\":{},\"method\":\"{}\",\"path\":\"/items/{}\",\"status\":{},\
+                     \"latency\":{}.{},\"tags\":[\"a\",\"b\"],\"user\":{}}}\n",
+                    1_600_000_000 + x * 3,
+                    ["GET", "POST"][x % 4 / 3],
+                    x * 17 % 50,
+                    [200, 404][x % 9 / 8],
+                    x % 40,
+                    x % 100,
+                    if x % 5 == 0 {
+                        "null"
+                    } else {
+                        "{\"id\": 5, \"admin\": false}"
+                    }
+                )
+                .into_bytes()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(b"{\"a\\\"\" : [1.5e3, true, \"x\"]}");
+        let kinds: Vec<Kind> = tokens.iter().map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Kind::ObjectStart,
+                Kind::Key,
+                Kind::Whitespace,
+                Kind::Colon,
+                Kind::Whitespace,
+                Kind::ArrayStart,
+                Kind::Number,
+                Kind::Comma,
+                Kind::Whitespace,
+                Kind::True,
+                Kind::Comma,
+                Kind::Whitespace,
+                Kind::String,
+                Kind::ArrayEnd,
+                Kind::ObjectEnd
+            ]
+        );
+        assert_eq!(tokens[1].contents, b"a\\\"");
+        assert_eq!(tokens[6].contents, b"1.5e3");
+        assert_eq!(tokens[12].contents, b"x");
+    }
+
+    #[test]
+    fn test_predicted_symbols() {
+        let symbols = [0, 1, 2, 0, 1, 2, 0, 2, 0, 1, 1];
+        let mut encoder = PredictedSymbols::new();
+        let mut previous = None;
+        for symbol in symbols.iter() {
+            encoder.push(previous, *symbol);
+            previous = Some(*symbol);
+        }
+        assert_eq!(encoder.run_lens, vec![0, 0, 0, 0, 3, 1, 0]);
+        assert_eq!(encoder.misses, vec![0, 1, 2, 0, 2, 1, 1]);
+
+        let streams = encoder.encode();
+        let mut decoder = PredictedSymbolsDecoder::new(streams[0].clone(), streams[1].clone());
+        let mut previous = None;
+        for symbol in symbols.iter() {
+            assert!(!decoder.is_finished());
+            assert_eq!(decoder.next(previous), *symbol);
+            previous = Some(*symbol);
+        }
+        assert!(decoder.is_finished());
+    }
+
+    #[test]
+    fn test_compress_decompress_json() {
+        let log = api_log();
+        let inputs = [
+            b"".to_vec(),
+            b"{}".to_vec(),
+            b"[1, -2.50, 3e-7, \"\\u00e4\\\\\", null]\r\n".to_vec(),
+            b"{\"nested\": {\"deep\": [[], {}]}, \"s\": \"\"}".to_vec(),
+            b"not json: \"unterminated {, tru fals".to_vec(),
+            log.clone(),
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&decompress_json(&compress_json(input)), input);
+        }
+
+        assert!(compress_json(&log).len() < compress(&log).len() / 8);
+    }
+
+    #[test]
+    fn test_matches_format() {
+        let log = api_log();
+        assert!(JsonCodec.matches_format(&log));
+        // a block that starts in the middle of a line
+        assert!(JsonCodec.matches_format(&log[10..]));
+        assert!(JsonCodec.matches_format(b" \n [1, 2]"));
+        assert!(!JsonCodec.matches_format(b"id,name\n1,{}\n"));
+    }
+}
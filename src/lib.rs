@@ -0,0 +1,35 @@
+pub mod auto;
+pub mod binarytree;
+mod blocksplit;
+pub mod bpe;
+pub mod canonical;
+pub mod codec;
+mod columns;
+mod compressor;
+pub mod csv;
+mod decompressor;
+pub mod digram;
+pub mod fasta;
+pub mod huffman;
+pub mod intcodec;
+pub mod json;
+pub mod logs;
+pub mod pipeline;
+pub mod pnm;
+pub mod rle;
+pub mod savebits;
+pub mod stats;
+pub mod stored;
+mod streams;
+pub mod timeseries;
+pub mod transform;
+pub mod universal;
+pub mod utf8;
+mod varint;
+pub mod wav;
+pub mod words;
+
+pub use crate::codec::{Codec, CodecRegistry};
+pub use crate::huffman::HuffmanCodec;
+pub use crate::pipeline::{compress, decompress, decompress_pipeline, Pipeline};
+pub use crate::transform::Transform;
@@ -0,0 +1,333 @@
+use crate::codec::Codec;
+use crate::columns::{decode_column, encode_column};
+use crate::huffman::HuffmanCodec;
+use crate::streams::{join_streams, split_streams};
+use std::collections::HashMap;
+
+/// A line is added to a template if at least this share of its tokens without digits
+/// are the same as the constant tokens of the template.
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// A template of log lines. Every token is either constant, or a slot that can have
+/// a different value on every line.
+type Template<'a> = Vec<Option<&'a [u8]>>;
+
+/// Compresses text logs by clustering the lines into templates. The lines are split
+/// into tokens at spaces, and every line is added to the most similar template with
+/// the same number of tokens, turning the tokens that differ into slots. Tokens with
+/// digits are always slots. The templates are stored once, and the values of every
+/// slot are coded as a column of their own.
+pub fn compress_logs(data: &[u8]) -> Vec<u8> {
+    let (lines, ends_with_newline) = split_lines(data);
+    let (templates, template_ids) = build_templates(&lines);
+
+    let usize_codec = HuffmanCodec::<usize>::new();
+    // counts and ids are offset by one, since zero can not be saved
+    let template_lens: Vec<usize> = templates
+        .iter()
+        .map(|template| template.len() + 1)
+        .collect();
+    let is_slot: Vec<u8> = templates
+        .iter()
+        .flatten()
+        .map(|token| token.is_none() as u8)
+        .collect();
+    let constants: Vec<&[u8]> = templates.iter().flatten().flatten().copied().collect();
+    let ids: Vec<usize> = template_ids.iter().map(|id| id + 1).collect();
+
+    let mut streams = vec![
+        vec![ends_with_newline as u8],
+        usize_codec.encode(&template_lens),
+        HuffmanCodec::<u8>::new().encode(&is_slot),
+        encode_column(&constants),
+        usize_codec.encode(&ids),
+    ];
+
+    let mut lines_by_template = vec![Vec::new(); templates.len()];
+    for (line, id) in lines.iter().zip(template_ids.iter()) {
+        lines_by_template[*id].push(line);
+    }
+    for (template, template_lines) in templates.iter().zip(lines_by_template) {
+        for (x, _) in template
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.is_none())
+        {
+            let column: Vec<&[u8]> = template_lines.iter().map(|line| line[x]).collect();
+            streams.push(encode_column(&column));
+        }
+    }
+
+    join_streams(&streams)
+}
+
+pub fn decompress_logs(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in log data");
+
+    let ends_with_newline = next_stream() == [1];
+    let usize_codec = HuffmanCodec::<usize>::new();
+    let template_lens = usize_codec.decode(next_stream());
+    let is_slot = HuffmanCodec::<u8>::new().decode(next_stream());
+    let num_constants = is_slot.iter().filter(|x| **x == 0).count();
+    let mut constants = decode_column(&next_stream(), num_constants).into_iter();
+    let template_ids: Vec<usize> = usize_codec
+        .decode(next_stream())
+        .into_iter()
+        .map(|id| id - 1)
+        .collect();
+
+    let mut is_slot = is_slot.into_iter();
+    let templates: Vec<Vec<Option<Vec<u8>>>> = template_lens
+        .iter()
+        .map(|len| {
+            (0..len - 1)
+                .map(
+                    |_| match is_slot.next().expect("Missing token in log template") {
+                        0 => Some(constants.next().expect("Missing token in log template")),
+                        _ => None,
+                    },
+                )
+                .collect()
+        })
+        .collect();
+
+    let mut num_lines = vec![0; templates.len()];
+    for id in template_ids.iter() {
+        *num_lines.get_mut(*id).expect("Unknown log template") += 1;
+    }
+    let mut slots: Vec<Vec<_>> = templates
+        .iter()
+        .zip(num_lines)
+        .map(|(template, len)| {
+            template
+                .iter()
+                .filter(|token| token.is_none())
+                .map(|_| decode_column(&next_stream(), len).into_iter())
+                .collect()
+        })
+        .collect();
+
+    let mut output = Vec::new();
+    for (x, template_id) in template_ids.into_iter().enumerate() {
+        if x > 0 {
+            output.push(b'\n');
+        }
+        let mut template_slots = slots[template_id].iter_mut();
+        for (x, token) in templates[template_id].iter().enumerate() {
+            if x > 0 {
+                output.push(b' ');
+            }
+            match token {
+                Some(token) => output.extend_from_slice(token),
+                None => output.extend(template_slots.next().unwrap().next().unwrap()),
+            }
+        }
+    }
+    if ends_with_newline {
+        output.push(b'\n');
+    }
+    output
+}
+
+/// Whether the data has at least two line breaks and almost no control bytes other
+/// than whitespace.
+fn is_text_lines(data: &[u8]) -> bool {
+    let num_line_breaks = data.iter().filter(|byte| **byte == b'\n').count();
+    let num_control = data
+        .iter()
+        .filter(|byte| byte.is_ascii_control() && !byte.is_ascii_whitespace())
+        .count();
+    num_line_breaks >= 2 && num_control * 100 <= data.len()
+}
+
+/// Splits the data into lines and the lines into tokens at spaces. Returns the lines
+/// and whether the last line ended in a line break.
+fn split_lines(data: &[u8]) -> (Vec<Vec<&[u8]>>, bool) {
+    let ends_with_newline = data.last() == Some(&b'\n');
+    let mut lines: Vec<&[u8]> = data.split(|byte| *byte == b'\n').collect();
+    if data.is_empty() || ends_with_newline {
+        lines.pop();
+    }
+    let lines = lines
+        .into_iter()
+        .map(|line| line.split(|byte| *byte == b' ').collect())
+        .collect();
+    (lines, ends_with_newline)
+}
+
+/// Clusters the lines into templates. Returns the templates and the template of
+/// every line.
+fn build_templates<'a>(lines: &[Vec<&'a [u8]>]) -> (Vec<Template<'a>>, Vec<usize>) {
+    let mut templates: Vec<Template> = Vec::new();
+    // templates are only compared to lines with the same length and first constant
+    let mut candidates_by_key: HashMap<(usize, Option<&[u8]>), Vec<usize>> = HashMap::new();
+    let mut template_ids = Vec::new();
+
+    for line in lines {
+        let first_constant = line.iter().copied().find(|token| !is_variable(token));
+        let candidates = candidates_by_key
+            .entry((line.len(), first_constant))
+            .or_default();
+        let best = candidates
+            .iter()
+            .map(|id| (similarity(&templates[*id], line), *id))
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let template_id = match best {
+            Some((similarity, id)) if similarity >= MIN_SIMILARITY => {
+                for (template_token, token) in templates[id].iter_mut().zip(line) {
+                    if *template_token != Some(*token) {
+                        *template_token = None;
+                    }
+                }
+                id
+            }
+            _ => {
+                templates.push(
+                    line.iter()
+                        .map(|token| Some(*token).filter(|token| !is_variable(token)))
+                        .collect(),
+                );
+                candidates.push(templates.len() - 1);
+                templates.len() - 1
+            }
+        };
+        template_ids.push(template_id);
+    }
+
+    (templates, template_ids)
+}
+
+/// Tokens with digits are likely to differ on every line, so they are always slots.
+fn is_variable(token: &[u8]) -> bool {
+    token.iter().any(u8::is_ascii_digit)
+}
+
+/// Share of the tokens of the line without digits that are the same as the constant
+/// tokens of the template.
+fn similarity(template: &[Option<&[u8]>], line: &[&[u8]]) -> f64 {
+    let num_constant = line.iter().filter(|token| !is_variable(token)).count();
+    if num_constant == 0 {
+        return 1.0;
+    }
+    let num_same = template
+        .iter()
+        .zip(line)
+        .filter(|(template_token, token)| **template_token == Some(**token))
+        .count();
+    num_same as f64 / num_constant as f64
+}
+
+/// Codes text logs as templates and the values of their slots.
+pub struct LogCodec;
+
+impl Codec for LogCodec {
+    fn name(&self) -> &'static str {
+        "logs"
+    }
+
+    fn id(&self) -> u8 {
+        10
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_logs(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_logs(data)
+    }
+
+    fn matches_format(&self, data: &[u8]) -> bool {
+        is_text_lines(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::compress;
+
+    fn app_log() -> Vec<u8> {
+        (0..500)
+            .flat_map(|x| {
+                let line = match x % 4 {
+                    0 => format!(
+                        "2021-03-04 12:{:02}:{:02} INFO Request to /api/items took {} ms",
+                        x / 60 % 60,
+                        x % 60,
+                        x * 7 % 300
+                    ),
+                    1 => format!(
+                        "2021-03-04 12:{:02}:{:02} INFO User {} logged in from {}",
+                        x / 60 % 60,
+                        x % 60,
+                        ["alice", "bob", "carol"][x % 3],
+                        ["web", "mobile"][x % 2]
+                    ),
+                    2 => format!(
+                        "2021-03-04 12:{:02}:{:02} WARN Cache miss for key user:{}",
+                        x / 60 % 60,
+                        x % 60,
+                        x % 50
+                    ),
+                    _ => format!(
+                        "2021-03-04 12:{:02}:{:02} ERROR Connection reset by peer",
+                        x / 60 % 60,
+                        x % 60
+                    ),
+                };
+                (line + "\n").into_bytes()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_templates() {
+        let data = b"connected to db in 5 ms\nconnected to cache in 12 ms\nshutting down\n";
+        let (lines, ends_with_newline) = split_lines(data);
+        assert!(ends_with_newline);
+        let (templates, template_ids) = build_templates(&lines);
+
+        assert_eq!(template_ids, vec![0, 0, 1]);
+        assert_eq!(
+            templates[0],
+            vec![
+                Some(&b"connected"[..]),
+                Some(b"to"),
+                None,
+                Some(b"in"),
+                None,
+                Some(b"ms")
+            ]
+        );
+        assert_eq!(templates[1], vec![Some(&b"shutting"[..]), Some(b"down")]);
+    }
+
+    #[test]
+    fn test_compress_decompress_logs() {
+        let log = app_log();
+        let inputs = [
+            b"".to_vec(),
+            b"\n".to_vec(),
+            b"\n\n  double  spaces \n".to_vec(),
+            b"no newline at the end".to_vec(),
+            b"a 1\na 2\r\nb\n".to_vec(),
+            log.clone(),
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&decompress_logs(&compress_logs(input)), input);
+        }
+
+        assert!(compress_logs(&log).len() < compress(&log).len() / 4);
+    }
+
+    #[test]
+    fn test_matches_format() {
+        let log = app_log();
+        assert!(LogCodec.matches_format(&log));
+        assert!(!LogCodec.matches_format(b"a single line of text"));
+        assert!(!LogCodec.matches_format(&[0, 1, 2, b'\n'].repeat(100)));
+    }
+}
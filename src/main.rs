@@ -1,15 +1,11 @@
 use std::env::args;
 use std::path::PathBuf;
 
-mod binarytree;
-mod compressor;
-mod decompressor;
 mod error;
-mod savebits;
 
-use crate::compressor::compress;
-use crate::decompressor::decompress;
 use crate::error::ProgramError;
+use compression::transform::transform_from_name;
+use compression::{decompress_pipeline, CodecRegistry, Pipeline};
 
 fn main() {
     if let Err(e) = run_program() {
@@ -18,7 +14,8 @@ fn main() {
 }
 
 fn run_program() -> Result<(), ProgramError> {
-    let args = parse_arguments()?;
+    let registry = CodecRegistry::default();
+    let args = parse_arguments(args().skip(1), &registry)?;
 
     let timer = if args.is_timed {
         Some(std::time::Instant::now())
@@ -39,7 +36,10 @@ fn run_program() -> Result<(), ProgramError> {
 
     match args.program_type {
         ProgramType::Compress => {
-            let compressed = compress(&input);
+            if args.is_verbose {
+                println!("Compressing with pipeline {}", args.pipeline.spec());
+            }
+            let compressed = args.pipeline.compress(&input);
             let compressed_len = compressed.len();
 
             println!(
@@ -60,7 +60,7 @@ fn run_program() -> Result<(), ProgramError> {
             }
         }
         ProgramType::Decompress => {
-            let decompressed = decompress(input);
+            let decompressed = decompress_pipeline(&input, &registry);
             let decompressed_len = decompressed.len();
 
             println!(
@@ -99,13 +99,16 @@ struct ProgramArgs {
     program_type: ProgramType,
     input_file: PathBuf,
     output_file: PathBuf,
+    pipeline: Pipeline,
     is_verbose: bool,
     is_timed: bool,
 }
 
-fn parse_arguments() -> Result<ProgramArgs, ProgramError> {
-    let mut args = args();
-    args.next();
+/// Parses the arguments after the program name.
+fn parse_arguments(
+    mut args: impl Iterator<Item = String>,
+    registry: &CodecRegistry,
+) -> Result<ProgramArgs, ProgramError> {
     let program_type = match args.next().as_deref() {
         Some("c") | Some("compress") => ProgramType::Compress,
         Some("d") | Some("decompress") => ProgramType::Decompress,
@@ -119,10 +122,34 @@ fn parse_arguments() -> Result<ProgramArgs, ProgramError> {
         Some(file_path) => PathBuf::from(file_path),
         None => return Err(ProgramError::InvalidArgumentsError),
     };
+    let mut codec = registry.get_by_name("huffman").unwrap();
+    let mut transforms = Vec::new();
+    let mut pipeline = None;
+    let mut has_method_or_transform = false;
     let mut is_verbose = false;
     let mut is_timed = false;
     while let Some(arg) = args.next().as_deref() {
         match arg {
+            "-m" | "--method" => {
+                let name = args.next().ok_or(ProgramError::InvalidArgumentsError)?;
+                codec = registry
+                    .get_by_name(&name)
+                    .ok_or(ProgramError::UnknownMethodError(name))?;
+                has_method_or_transform = true;
+            }
+            "--transform" => {
+                let names = args.next().ok_or(ProgramError::InvalidArgumentsError)?;
+                for name in names.split(',') {
+                    let transform = transform_from_name(name)
+                        .ok_or_else(|| ProgramError::UnknownTransformError(name.to_string()))?;
+                    transforms.push(transform);
+                }
+                has_method_or_transform = true;
+            }
+            "-p" | "--pipeline" => {
+                let spec = args.next().ok_or(ProgramError::InvalidArgumentsError)?;
+                pipeline = Some(Pipeline::from_spec(&spec, registry)?);
+            }
             "-v" => is_verbose = true,
             "-t" => is_timed = true,
             _ => eprintln!(
@@ -131,10 +158,19 @@ fn parse_arguments() -> Result<ProgramArgs, ProgramError> {
             ),
         }
     }
+    // the pipeline already names the codec and the transforms
+    if pipeline.is_some() && has_method_or_transform {
+        return Err(ProgramError::ConflictingPipelineError);
+    }
+    // decompressing reads the pipeline from the compressed data
+    if pipeline.is_some() && matches!(program_type, ProgramType::Decompress) {
+        return Err(ProgramError::InvalidArgumentsError);
+    }
     Ok(ProgramArgs {
         program_type,
         input_file,
         output_file,
+        pipeline: pipeline.unwrap_or_else(|| Pipeline::new(transforms, codec)),
         is_verbose,
         is_timed,
     })
@@ -143,14 +179,55 @@ fn parse_arguments() -> Result<ProgramArgs, ProgramError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use compression::{compress, decompress};
+
+    fn parse(args: &str) -> Result<ProgramArgs, ProgramError> {
+        let args = args.split(' ').map(|arg| arg.to_string());
+        parse_arguments(args, &CodecRegistry::default())
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let args = parse("c in out -m words --transform bwt,mtf -v")
+            .ok()
+            .unwrap();
+        assert_eq!(args.pipeline.spec(), "bwt,mtf,words");
+        assert!(args.is_verbose);
+        let args = parse("c in out -p delta,rle").ok().unwrap();
+        assert_eq!(args.pipeline.spec(), "delta,rle");
+
+        for args in [
+            "c in out -p delta,rle -m words",
+            "c in out -m words -p rle",
+            "c in out --transform bwt -p rle",
+        ] {
+            assert!(matches!(
+                parse(args),
+                Err(ProgramError::ConflictingPipelineError)
+            ));
+        }
+        for args in ["c in", "d in out -p delta,rle"] {
+            assert!(matches!(
+                parse(args),
+                Err(ProgramError::InvalidArgumentsError)
+            ));
+        }
+    }
 
     #[test]
     fn test_compression_decompression() {
-        let inputs = vec![
+        let inputs = [
             b"".to_vec(),
             b"a".to_vec(),
             b"aaaa".to_vec(),
             b"hello world".to_vec(),
+            [
+                vec![b'x'; 5000],
+                b"0123456789".repeat(500),
+                vec![b'y'; 3000],
+            ]
+            .concat(),
+            [b"ab".repeat(25), b"xyz".repeat(17)].concat().repeat(20),
         ];
 
         for input in inputs {
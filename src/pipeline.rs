@@ -0,0 +1,234 @@
+use crate::codec::{Codec, CodecRegistry};
+use crate::huffman::HuffmanCodec;
+use crate::stored::StoredCodec;
+use crate::transform::{transform_from_id, transform_from_name, transform_spec, Transform};
+use std::rc::Rc;
+
+/// A sequence of transforms followed by the codec the transformed data is
+/// compressed with. The pipeline is stored at the start of the compressed data
+/// as the id of the codec, the number of transforms and the ids of the transforms.
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform>>,
+    codec: Rc<dyn Codec>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PipelineSpecError {
+    UnknownTransform(String),
+    UnknownMethod(String),
+}
+
+impl Pipeline {
+    pub fn new(transforms: Vec<Box<dyn Transform>>, codec: Rc<dyn Codec>) -> Pipeline {
+        Pipeline { transforms, codec }
+    }
+
+    /// Parses a comma-separated list of transform names followed by the name of
+    /// a codec in the registry, such as `delta,bwt,mtf,huffman`. Transforms can
+    /// be given a parameter after a colon, such as `idelta:4`.
+    pub fn from_spec(spec: &str, registry: &CodecRegistry) -> Result<Pipeline, PipelineSpecError> {
+        let mut names: Vec<&str> = spec.split(',').collect();
+        let codec_name = names.pop().unwrap();
+        let codec = registry
+            .get_by_name(codec_name)
+            .ok_or_else(|| PipelineSpecError::UnknownMethod(codec_name.to_string()))?;
+        let transforms = names
+            .into_iter()
+            .map(|name| {
+                transform_from_name(name)
+                    .ok_or_else(|| PipelineSpecError::UnknownTransform(name.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Pipeline { transforms, codec })
+    }
+
+    /// Returns the specification of the pipeline in the format accepted by `from_spec`.
+    pub fn spec(&self) -> String {
+        self.transforms
+            .iter()
+            .map(|transform| transform_spec(transform.as_ref()))
+            .chain(std::iter::once(self.codec.name().to_string()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Compresses the data with the pipeline. If that would make the data larger, the
+    /// data is stored as it is instead, which only adds the two bytes of the header.
+    /// Every transform adds its id and parameter to the header.
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        let mut transformed = data.to_vec();
+        for transform in self.transforms.iter() {
+            transformed = transform.forward(&transformed);
+        }
+        let encoded = self.codec.encode(&transformed);
+
+        if encoded.len() + 2 * self.transforms.len() >= data.len() {
+            let mut output = vec![StoredCodec.id(), 0];
+            output.extend_from_slice(data);
+            return output;
+        }
+
+        let mut output = vec![self.codec.id(), self.transforms.len() as u8];
+        for transform in self.transforms.iter() {
+            output.extend_from_slice(&[transform.id(), transform.parameter()]);
+        }
+        output.extend(encoded);
+        output
+    }
+
+    /// Reads the pipeline from the start of compressed data. Returns the pipeline
+    /// and the rest of the data.
+    fn from_header<'a>(data: &'a [u8], registry: &CodecRegistry) -> (Pipeline, &'a [u8]) {
+        let (codec_id, data) = data.split_first().expect("Compressed data is empty");
+        let codec = registry
+            .get_by_id(*codec_id)
+            .expect("Unknown compression method");
+        let (num_transforms, data) = data.split_first().expect("Compressed data is too short");
+        let (transform_ids, data) = data.split_at(2 * *num_transforms as usize);
+        let transforms = transform_ids
+            .chunks(2)
+            .map(|id| transform_from_id(id[0], id[1]).expect("Unknown transform"))
+            .collect();
+
+        (Pipeline { transforms, codec }, data)
+    }
+}
+
+/// Compresses the data with Huffman coding. Like every pipeline, data that would grow
+/// is stored as it is instead.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    Pipeline::new(Vec::new(), Rc::new(HuffmanCodec::<u8>::new())).compress(data)
+}
+
+/// Decompresses data compressed with `compress` or any pipeline of the default codecs.
+pub fn decompress(data: Vec<u8>) -> Vec<u8> {
+    decompress_pipeline(&data, &CodecRegistry::default())
+}
+
+/// Decompresses data compressed with `Pipeline::compress`. The pipeline is read from
+/// the compressed data and reversed. The codec of the pipeline must be in the registry.
+pub fn decompress_pipeline(data: &[u8], registry: &CodecRegistry) -> Vec<u8> {
+    let (pipeline, data) = Pipeline::from_header(data, registry);
+
+    let mut decompressed = pipeline.codec.decode(data);
+    for transform in pipeline.transforms.iter().rev() {
+        decompressed = transform.inverse(&decompressed);
+    }
+    decompressed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_spec() {
+        let registry = CodecRegistry::default();
+        let spec = |spec| Pipeline::from_spec(spec, &registry);
+
+        assert_eq!(
+            spec("delta,bwt,mtf,huffman").unwrap().spec(),
+            "delta,bwt,mtf,huffman"
+        );
+        assert_eq!(spec("words").unwrap().spec(), "words");
+        assert_eq!(
+            spec("mtf,bwt").err(),
+            Some(PipelineSpecError::UnknownMethod("bwt".to_string()))
+        );
+        assert_eq!(
+            spec("mtf,huffman,rle").err(),
+            Some(PipelineSpecError::UnknownTransform("huffman".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_compress_decompress_pipeline() {
+        let input = b"one fish two fish red fish blue fish".repeat(10);
+
+        let registry = CodecRegistry::default();
+
+        for spec in ["huffman", "distance,mtf,huffman", "delta,bwt,mtf,rle"].iter() {
+            let compressed = Pipeline::from_spec(spec, &registry)
+                .unwrap()
+                .compress(&input);
+            assert_eq!(decompress_pipeline(&compressed, &registry), input);
+        }
+
+        let compressed = Pipeline::from_spec("bwt,mtf,digram", &registry)
+            .unwrap()
+            .compress(&input);
+        assert_eq!(compressed[..6], [4, 2, 3, 0, 0, 0]);
+
+        let samples: Vec<u8> = (0..2000u16)
+            .flat_map(|x| (x * 3 + x % 7).to_le_bytes())
+            .collect();
+        let pipeline = Pipeline::from_spec("idelta:2,xor:2,huffman", &registry).unwrap();
+        assert_eq!(pipeline.spec(), "idelta,xor,huffman");
+        let compressed = pipeline.compress(&samples);
+        assert_eq!(compressed[..6], [0, 2, 4, 2, 5, 2]);
+        assert_eq!(decompress_pipeline(&compressed, &registry), samples);
+
+        let compressed = Pipeline::from_spec("delta:2,huffman", &registry)
+            .unwrap()
+            .compress(&samples);
+        assert_eq!(compressed[..4], [0, 1, 2, 2]);
+        assert_eq!(decompress_pipeline(&compressed, &registry), samples);
+    }
+
+    #[test]
+    fn test_shuffled_floats() {
+        let registry = CodecRegistry::default();
+        let values: Vec<u8> = (0..2000)
+            .flat_map(|x| (20.0 + (x as f64 / 100.0).sin()).to_le_bytes())
+            .collect();
+        let compressed_len = |spec| {
+            let compressed = Pipeline::from_spec(spec, &registry)
+                .unwrap()
+                .compress(&values);
+            assert_eq!(decompress_pipeline(&compressed, &registry), values);
+            compressed.len()
+        };
+
+        // the sign, exponent and high mantissa bytes of the values end up in runs
+        assert!(compressed_len("shuffle,rle") < compressed_len("rle") * 9 / 10);
+        assert!(compressed_len("bitshuffle,rle") < compressed_len("rle") * 9 / 10);
+    }
+
+    #[test]
+    fn test_incompressible_data_is_stored() {
+        let registry = CodecRegistry::default();
+        let mut state = 12345u32;
+        let input: Vec<u8> = (0..10_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+
+        for spec in ["huffman", "bwt,mtf,huffman", "words"].iter() {
+            let compressed = Pipeline::from_spec(spec, &registry)
+                .unwrap()
+                .compress(&input);
+            assert_eq!(compressed.len(), input.len() + 2);
+            assert_eq!(decompress_pipeline(&compressed, &registry), input);
+        }
+        for input in [vec![], vec![42]].iter() {
+            let compressed = Pipeline::from_spec("huffman", &registry)
+                .unwrap()
+                .compress(input);
+            assert_eq!(compressed, [&[7, 0][..], input].concat());
+        }
+
+        // compress uses the same fallback
+        let compressed = compress(&input);
+        assert_eq!(compressed[..2], [StoredCodec.id(), 0]);
+        assert_eq!(compressed.len(), input.len() + 2);
+        assert_eq!(decompress(compressed), input);
+        let text = b"abracadabra abracadabra abracadabra".to_vec();
+        let compressed = compress(&text);
+        assert_eq!(compressed[..2], [HuffmanCodec::<u8>::new().id(), 0]);
+        assert!(compressed.len() < text.len());
+        assert_eq!(decompress(compressed), text);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::codec::Codec;
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::huffman::HuffmanCodec;
+use crate::streams::{join_streams, split_streams};
+
+/// The prediction filters of PNG. Every filter predicts a byte from the byte of the
+/// previous pixel, the byte above it and the byte above the previous pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    None = 0,
+    Sub = 1,
+    Up = 2,
+    Average = 3,
+    Paeth = 4,
+}
+
+const FILTERS: [Filter; 5] = [
+    Filter::None,
+    Filter::Sub,
+    Filter::Up,
+    Filter::Average,
+    Filter::Paeth,
+];
+
+impl Filter {
+    fn predict(self, left: u8, up: u8, up_left: u8) -> u8 {
+        match self {
+            Filter::None => 0,
+            Filter::Sub => left,
+            Filter::Up => up,
+            Filter::Average => ((left as u16 + up as u16) / 2) as u8,
+            Filter::Paeth => paeth(left, up, up_left),
+        }
+    }
+}
+
+/// Predicts with the one of the neighbours that is closest to `left + up - up_left`.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance = |byte: u8| (estimate - byte as i16).abs();
+    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+        left
+    } else if distance(up) <= distance(up_left) {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// The layout of the pixels of a binary PGM or PPM image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Image {
+    /// The length of the header in bytes, including the whitespace after it.
+    header_len: usize,
+    row_len: usize,
+    height: usize,
+    /// The number of bytes in a pixel.
+    pixel_len: usize,
+}
+
+/// Parses the header of a binary PGM (`P5`) or PPM (`P6`) image. Returns `None` if
+/// the data does not start with such a header or the image has no pixels.
+fn parse_header(data: &[u8]) -> Option<Image> {
+    let num_channels = match data.get(..2)? {
+        b"P5" => 1,
+        b"P6" => 3,
+        _ => return None,
+    };
+
+    let mut pos = 2;
+    let mut fields = [0usize; 3];
+    for field in fields.iter_mut() {
+        // fields are separated by whitespace and comments that last until the line end
+        loop {
+            match data.get(pos)? {
+                byte if byte.is_ascii_whitespace() => pos += 1,
+                b'#' => {
+                    while *data.get(pos)? != b'\n' {
+                        pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        let len = data[pos..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        *field = std::str::from_utf8(&data[pos..pos + len])
+            .ok()?
+            .parse()
+            .ok()?;
+        pos += len;
+    }
+    // a single whitespace byte separates the header from the pixels
+    if !data.get(pos)?.is_ascii_whitespace() {
+        return None;
+    }
+
+    let [width, height, max_value] = fields;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let sample_len = match max_value {
+        1..=255 => 1,
+        256..=65535 => 2,
+        _ => return None,
+    };
+    let image = Image {
+        header_len: pos + 1,
+        row_len: width.checked_mul(num_channels * sample_len)?,
+        height,
+        pixel_len: num_channels * sample_len,
+    };
+    image
+        .row_len
+        .checked_mul(height)?
+        .checked_add(image.header_len)?;
+    Some(image)
+}
+
+/// Compresses binary PGM and PPM images. Every row of pixels is filtered with the
+/// PNG filter that gives the smallest sum of absolute differences, and the
+/// differences are Huffman coded. The header and any bytes after the pixels are
+/// kept as they are. Other data is compressed with Huffman coding.
+pub fn compress_pnm(data: &[u8]) -> Vec<u8> {
+    // the pixels must fit in the data before any buffers are sized by the header
+    let image = parse_header(data)
+        .filter(|image| image.header_len + image.row_len * image.height <= data.len());
+    let image = match image {
+        Some(image) => image,
+        None => return join_streams(&[Vec::new(), compress(data)]),
+    };
+
+    let pixels_end = image.header_len + image.row_len * image.height;
+    let pixels = &data[image.header_len..pixels_end];
+    let mut filters = Vec::with_capacity(image.height);
+    let mut residuals = Vec::with_capacity(pixels.len());
+    let mut candidate = vec![0; image.row_len];
+    let mut best = vec![0; image.row_len];
+    for y in 0..image.height {
+        let mut best_cost = u64::MAX;
+        let mut best_filter = Filter::None;
+        for filter in FILTERS.iter() {
+            filter_row(*filter, pixels, &image, y, &mut candidate);
+            let cost = candidate
+                .iter()
+                .map(|byte| (*byte as i8).unsigned_abs() as u64)
+                .sum();
+            if cost < best_cost {
+                best_cost = cost;
+                best_filter = *filter;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        filters.push(best_filter as u8);
+        residuals.extend_from_slice(&best);
+    }
+
+    join_streams(&[
+        data[..image.header_len].to_vec(),
+        HuffmanCodec::<u8>::new().encode(&filters),
+        compress(&residuals),
+        compress(&data[pixels_end..]),
+    ])
+}
+
+pub fn decompress_pnm(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in PNM data");
+
+    let mut output = next_stream();
+    if output.is_empty() {
+        return decompress(next_stream());
+    }
+    let image = parse_header(&output).expect("Invalid PNM header");
+    let filters = HuffmanCodec::<u8>::new().decode(next_stream());
+    let residuals = decompress(next_stream());
+
+    let mut pixels = vec![0; image.row_len * image.height];
+    for (y, filter) in filters.into_iter().enumerate() {
+        let filter = *FILTERS.get(filter as usize).expect("Unknown PNM filter");
+        let row_start = y * image.row_len;
+        for x in 0..image.row_len {
+            let (left, up, up_left) = neighbours(&pixels, &image, y, x);
+            pixels[row_start + x] =
+                residuals[row_start + x].wrapping_add(filter.predict(left, up, up_left));
+        }
+    }
+    output.extend(pixels);
+    output.extend(decompress(next_stream()));
+    output
+}
+
+/// Writes the differences between the bytes of the row and their predictions.
+fn filter_row(filter: Filter, pixels: &[u8], image: &Image, y: usize, output: &mut [u8]) {
+    let row_start = y * image.row_len;
+    for (x, residual) in output.iter_mut().enumerate() {
+        let (left, up, up_left) = neighbours(pixels, image, y, x);
+        *residual = pixels[row_start + x].wrapping_sub(filter.predict(left, up, up_left));
+    }
+}
+
+/// Returns the bytes of the previous pixel, the pixel above and the pixel above the
+/// previous one. Bytes outside the image are zero.
+fn neighbours(pixels: &[u8], image: &Image, y: usize, x: usize) -> (u8, u8, u8) {
+    let pos = y * image.row_len + x;
+    let has_left = x >= image.pixel_len;
+    let left = if has_left {
+        pixels[pos - image.pixel_len]
+    } else {
+        0
+    };
+    let up = if y > 0 {
+        pixels[pos - image.row_len]
+    } else {
+        0
+    };
+    let up_left = if has_left && y > 0 {
+        pixels[pos - image.row_len - image.pixel_len]
+    } else {
+        0
+    };
+    (left, up, up_left)
+}
+
+/// Codes binary PGM and PPM images with prediction filters.
+pub struct PnmCodec;
+
+impl Codec for PnmCodec {
+    fn name(&self) -> &'static str {
+        "pnm"
+    }
+
+    fn id(&self) -> u8 {
+        12
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_pnm(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_pnm(data)
+    }
+
+    fn matches_format(&self, data: &[u8]) -> bool {
+        parse_header(data)
+            .is_some_and(|image| image.header_len + image.row_len * image.height <= data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A smooth color image with some noise.
+    fn ppm(width: usize, height: usize) -> Vec<u8> {
+        let mut image = format!("P6\n# camera frame\n{} {}\n255\n", width, height).into_bytes();
+        for y in 0..height {
+            for x in 0..width {
+                let noise = ((x * 7 + y * 13) % 5) as u8;
+                image.push((x * 250 / width) as u8 + noise);
+                image.push((y * 255 / height) as u8);
+                image.push(((x + y) * 100 / (width + height)) as u8 + noise);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let image = parse_header(b"P5 3\n2 #comment\n65535\r123456789012").unwrap();
+        assert_eq!(
+            image,
+            Image {
+                header_len: 22,
+                row_len: 6,
+                height: 2,
+                pixel_len: 2
+            }
+        );
+        assert!(parse_header(b"P6 1 1 0 abc").is_none());
+        assert!(parse_header(b"P3 1 1 255 1 2 3").is_none());
+        assert!(parse_header(b"P5 1 1 255").is_none());
+        assert!(parse_header(b"P5 100000000000000 0 255\n").is_none());
+        assert!(parse_header(b"P5 0 100000000000000 255\n").is_none());
+    }
+
+    #[test]
+    fn test_paeth() {
+        assert_eq!(paeth(10, 20, 10), 20);
+        assert_eq!(paeth(20, 10, 10), 20);
+        assert_eq!(paeth(10, 10, 20), 10);
+        assert_eq!(paeth(0, 255, 128), 128);
+    }
+
+    #[test]
+    fn test_compress_decompress_pnm() {
+        let image = ppm(120, 80);
+        let with_trailing = [ppm(5, 5), b"trailing".to_vec()].concat();
+        let inputs = [
+            b"".to_vec(),
+            b"P6 not an image".to_vec(),
+            b"P5 0 0 255\n".to_vec(),
+            b"P5 2 2 255 truncated".to_vec(),
+            b"P5 100000000000000 0 255\n".to_vec(),
+            b"P6 100000000000 1 255\n\x01\x02\x03".to_vec(),
+            b"P5 2 2 65535 \x01\x02\x03\x04\x05\x06\x07\x08".to_vec(),
+            with_trailing,
+            image.clone(),
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&decompress_pnm(&compress_pnm(input)), input);
+        }
+
+        assert!(compress_pnm(&image).len() < compress(&image).len() / 2);
+        assert!(PnmCodec.matches_format(&image));
+        assert!(!PnmCodec.matches_format(&image[..image.len() - 1]));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::codec::Codec;
+use crate::huffman::HuffmanCodec;
+use crate::savebits::SaveBits;
+use crate::stats::DataStats;
+
+/// A symbol of run-length encoded data. Every run starts with the byte of the run,
+/// and the number of times it is repeated after that is written in bijective base 2
+/// with `RunA` as the digit one and `RunB` as the digit two, starting from the least
+/// significant digit. A run of n bytes therefore takes about log2(n) symbols.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum RleSymbol {
+    Byte(u8),
+    RunA,
+    RunB,
+}
+
+impl SaveBits for RleSymbol {
+    fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+        match *self {
+            RleSymbol::Byte(byte) => Box::new(std::iter::once(false).chain(byte.save_bits())),
+            RleSymbol::RunA => Box::new([true, false].iter().copied()),
+            RleSymbol::RunB => Box::new([true, true].iter().copied()),
+        }
+    }
+
+    fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+        match iter.next() {
+            Some(false) => RleSymbol::Byte(u8::from_bits(iter)),
+            Some(true) => match iter.next() {
+                Some(false) => RleSymbol::RunA,
+                Some(true) => RleSymbol::RunB,
+                None => panic!("Iterator returned None while loading RleSymbol"),
+            },
+            None => panic!("Iterator returned None while loading RleSymbol"),
+        }
+    }
+}
+
+pub fn rle_encode(data: &[u8]) -> Vec<RleSymbol> {
+    let mut symbols = Vec::new();
+    let mut x = 0;
+    while x < data.len() {
+        let run_len = data[x..]
+            .iter()
+            .take_while(|&&byte| byte == data[x])
+            .count();
+        symbols.push(RleSymbol::Byte(data[x]));
+
+        let mut repeats = run_len - 1;
+        while repeats > 0 {
+            if repeats % 2 == 1 {
+                symbols.push(RleSymbol::RunA);
+                repeats = (repeats - 1) / 2;
+            } else {
+                symbols.push(RleSymbol::RunB);
+                repeats = (repeats - 2) / 2;
+            }
+        }
+
+        x += run_len;
+    }
+    symbols
+}
+
+pub fn rle_decode(symbols: &[RleSymbol]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut x = 0;
+    while x < symbols.len() {
+        let byte = match symbols[x] {
+            RleSymbol::Byte(byte) => byte,
+            _ => panic!("Run length encoded data has a run without a byte"),
+        };
+        x += 1;
+
+        let mut repeats = 0;
+        let mut digit_weight = 1;
+        while let Some(symbol) = symbols.get(x) {
+            match symbol {
+                RleSymbol::RunA => repeats += digit_weight,
+                RleSymbol::RunB => repeats += 2 * digit_weight,
+                RleSymbol::Byte(_) => break,
+            }
+            digit_weight *= 2;
+            x += 1;
+        }
+
+        data.extend(std::iter::repeat_n(byte, repeats + 1));
+    }
+    data
+}
+
+/// Compresses the data by Huffman coding its run-length encoded symbols, so that
+/// long runs of the same byte take only a few bits.
+pub fn compress_rle(data: &[u8]) -> Vec<u8> {
+    HuffmanCodec::new().encode(&rle_encode(data))
+}
+
+pub fn decompress_rle(data: &[u8]) -> Vec<u8> {
+    rle_decode(&HuffmanCodec::new().decode(data.to_vec()))
+}
+
+/// Codes run-length encoded bytes.
+pub struct RleCodec;
+
+impl Codec for RleCodec {
+    fn name(&self) -> &'static str {
+        "rle"
+    }
+
+    fn id(&self) -> u8 {
+        5
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_rle(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_rle(data)
+    }
+
+    fn estimate_size(&self, stats: &DataStats) -> Option<usize> {
+        // a run of n bytes takes about log2(n) run symbols of one or two bits
+        let bits = stats.run_entropy
+            + 1.5 * stats.run_length_bits
+            + 10.0 * (stats.distinct_bytes + 2) as f64;
+        Some((bits / 8.0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rle_encode() {
+        use RleSymbol::*;
+
+        assert_eq!(rle_encode(b"ab"), vec![Byte(b'a'), Byte(b'b')]);
+        assert_eq!(rle_encode(b"aa"), vec![Byte(b'a'), RunA]);
+        assert_eq!(rle_encode(b"aaa"), vec![Byte(b'a'), RunB]);
+        assert_eq!(rle_encode(b"aaaa"), vec![Byte(b'a'), RunA, RunA]);
+        assert_eq!(
+            rle_encode(b"aaaaaab"),
+            vec![Byte(b'a'), RunA, RunB, Byte(b'b')]
+        );
+        assert_eq!(rle_encode(&[0; 1 << 20]).len(), 21);
+    }
+
+    #[test]
+    fn test_compress_decompress_rle() {
+        let inputs = [
+            Vec::new(),
+            vec![7],
+            vec![0; 1 << 20],
+            [vec![1; 1000], vec![2; 3], vec![1; 1001], b"xyz".repeat(5)].concat(),
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(&rle_decode(&rle_encode(input)), input);
+            assert_eq!(&decompress_rle(&compress_rle(input)), input);
+        }
+        assert!(compress_rle(&inputs[2]).len() < 20);
+    }
+}
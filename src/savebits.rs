@@ -1,58 +1,100 @@
+use crate::universal::{EliasGamma, UniversalCode};
+
 pub trait SaveBits {
     fn save_bits(&self) -> Box<dyn Iterator<Item = bool>>;
     fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self;
 }
 
-impl SaveBits for u8 {
+/// Number of bits needed for the value without its leading zeros.
+pub fn bit_width(value: u64) -> u32 {
+    u64::BITS - value.leading_zeros()
+}
+
+/// Saves the lowest `width` bits of the value, starting from the most significant one.
+pub fn save_bits_with_width(value: u64, width: u32) -> impl Iterator<Item = bool> {
+    (0..width).rev().map(move |x| (value >> x) & 1 == 1)
+}
+
+/// Loads a value saved with `save_bits_with_width`.
+pub fn load_bits_with_width(iter: &mut dyn Iterator<Item = bool>, width: u32) -> u64 {
+    let mut value = 0;
+    for _ in 0..width {
+        let is_bit_set = iter
+            .next()
+            .expect("Iterator returned None while loading bits");
+        value = value << 1 | is_bit_set as u64;
+    }
+    value
+}
+
+/// Implements `SaveBits` for an unsigned integer type by saving all of its bits,
+/// starting from the most significant one.
+macro_rules! impl_save_bits_for_uint {
+    ($($t:ty),*) => {
+        $(
+            impl SaveBits for $t {
+                fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+                    let s = *self;
+                    Box::new((0..<$t>::BITS).rev().map(move |x| (1 << x) & s > 0))
+                }
+
+                fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+                    let mut s = 0;
+                    for x in (0..<$t>::BITS).rev() {
+                        let is_bit_set = iter.next().expect(concat!(
+                            "Iterator returned None while loading ",
+                            stringify!($t)
+                        ));
+                        if is_bit_set {
+                            s |= 1 << x;
+                        }
+                    }
+                    s
+                }
+            }
+        )*
+    };
+}
+
+impl_save_bits_for_uint!(u8, u16, u32, u64);
+
+/// Floats are saved as their bit patterns, so that NaN payloads are kept.
+macro_rules! impl_save_bits_for_float {
+    ($($t:ty: $bits:ty),*) => {
+        $(
+            impl SaveBits for $t {
+                fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+                    self.to_bits().save_bits()
+                }
+
+                fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+                    <$t>::from_bits(<$bits>::from_bits(iter))
+                }
+            }
+        )*
+    };
+}
+
+impl_save_bits_for_float!(f32: u32, f64: u64);
+
+impl SaveBits for char {
     fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
-        let s = *self;
-        Box::new((0..8).rev().map(move |x| (1 << x) & s > 0))
+        (*self as u32).save_bits()
     }
 
     fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
-        let mut s = 0;
-        for x in (0..8).rev() {
-            let is_bit_set = iter
-                .next()
-                .expect("Iterator returned None while loading u8");
-            if is_bit_set {
-                s |= 1 << x;
-            }
-        }
-        s
+        std::char::from_u32(u32::from_bits(iter)).expect("Loaded an invalid char")
     }
 }
 
+/// Saved with the Elias gamma code, so zero can not be saved.
 impl SaveBits for usize {
     fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
-        assert!(*self > 0);
-        let num_bits = std::mem::size_of::<usize>() as u32 * 8 - self.leading_zeros();
-        let s = *self;
-        Box::new((0..2 * num_bits - 1).map(move |x| {
-            x == num_bits - 1 || (x >= num_bits && (1 << (2 * num_bits - x - 2)) & s != 0)
-        }))
+        EliasGamma.encode(*self as u64)
     }
 
     fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
-        let mut num_bits = 0;
-        let mut last_bit = false;
-        while !last_bit {
-            num_bits += 1;
-            last_bit = iter
-                .next()
-                .expect("Iterator returned None while loading usize");
-        }
-        let mut s = 1 << (num_bits - 1);
-        for x in (0..num_bits - 1).rev() {
-            if iter
-                .next()
-                .expect("Iterator returned None while loading usize")
-            {
-                s |= 1 << x;
-            }
-        }
-
-        s
+        EliasGamma.decode(iter) as usize
     }
 }
 
@@ -113,6 +155,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_save_load_wide_uints() {
+        assert_eq!(16, 0xbeefu16.save_bits().count());
+        assert_eq!(32, 7u32.save_bits().count());
+        assert_eq!(64, u64::MAX.save_bits().count());
+
+        for k in [0u16, 1, 255, 256, 0xbeef, u16::MAX].iter() {
+            assert_eq!(*k, u16::from_bits(&mut k.save_bits()));
+        }
+        for k in [0u32, 1, 0xdead_beef, u32::MAX].iter() {
+            assert_eq!(*k, u32::from_bits(&mut k.save_bits()));
+        }
+        for k in [0u64, 1, 0x0123_4567_89ab_cdef, u64::MAX].iter() {
+            assert_eq!(*k, u64::from_bits(&mut k.save_bits()));
+        }
+        for c in ['a', 'ä', '語', '🦀'].iter() {
+            assert_eq!(*c, char::from_bits(&mut c.save_bits()));
+        }
+        for f in [0.0f32, -1.5, f32::INFINITY].iter() {
+            assert_eq!(*f, <f32 as SaveBits>::from_bits(&mut f.save_bits()));
+        }
+        assert_eq!(64, 0.1f64.save_bits().count());
+        assert_eq!(0.1, <f64 as SaveBits>::from_bits(&mut 0.1f64.save_bits()));
+    }
+
+    #[test]
+    fn test_save_load_bits_with_width() {
+        assert_eq!(
+            vec![true, false, true],
+            save_bits_with_width(5, 3).collect::<Vec<_>>()
+        );
+        assert_eq!(0, save_bits_with_width(5, 0).count());
+        for (value, width) in [(0, 1), (5, 7), (1000, 10), (u64::MAX, 64)].iter() {
+            let mut bits = save_bits_with_width(*value, *width);
+            assert_eq!(*value, load_bits_with_width(&mut bits, *width));
+        }
+    }
+
     #[test]
     fn test_from_bits_usize() {
         assert_eq!(usize::from_bits(&mut [true].iter().copied()), 1usize);
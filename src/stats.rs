@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+/// Minimum length of a repeated sequence counted by `DataStats::match_ratio`.
+const MIN_MATCH_LEN: usize = 4;
+
+/// Statistics of a piece of data that codecs use for estimating how well they
+/// would compress it. All of the statistics are computed in linear time.
+pub struct DataStats {
+    pub len: usize,
+    /// Order-0 entropy of the bytes in bits.
+    pub byte_entropy: f64,
+    pub distinct_bytes: usize,
+    /// Number of runs of identical bytes.
+    pub num_runs: usize,
+    /// Order-0 entropy of the first bytes of the runs in bits.
+    pub run_entropy: f64,
+    /// Sum of the base-2 logarithms of the run lengths.
+    pub run_length_bits: f64,
+    /// Order-0 entropy of the non-overlapping byte pairs in bits.
+    pub digram_entropy: f64,
+    pub distinct_digrams: usize,
+    /// Order-0 entropy of the code points in bits, if the data is mostly valid UTF-8.
+    pub code_point_entropy: Option<f64>,
+    pub distinct_code_points: usize,
+    /// Fraction of the bytes that are covered by repeats of earlier sequences of at
+    /// least four bytes, found greedily like an LZ77 parser with a single hash chain
+    /// entry would find them.
+    pub match_ratio: f64,
+}
+
+impl DataStats {
+    pub fn new(data: &[u8]) -> DataStats {
+        let mut byte_counts = [0; 256];
+        let mut run_counts = [0; 256];
+        let mut num_runs = 0;
+        let mut run_length_bits = 0.0;
+        let mut x = 0;
+        while x < data.len() {
+            let run_len = data[x..]
+                .iter()
+                .take_while(|&&byte| byte == data[x])
+                .count();
+            byte_counts[data[x] as usize] += run_len;
+            run_counts[data[x] as usize] += 1;
+            num_runs += 1;
+            run_length_bits += (run_len as f64).log2();
+            x += run_len;
+        }
+
+        let mut digram_counts = vec![0; 1 << 16];
+        for pair in data.chunks_exact(2) {
+            digram_counts[(pair[0] as usize) << 8 | pair[1] as usize] += 1;
+        }
+
+        let mut code_point_counts = HashMap::new();
+        let text = String::from_utf8_lossy(data);
+        for c in text.chars() {
+            *code_point_counts.entry(c).or_insert(0) += 1;
+        }
+        let num_invalid = code_point_counts
+            .get(&std::char::REPLACEMENT_CHARACTER)
+            .copied()
+            .unwrap_or(0);
+        let code_point_entropy = if num_invalid * 100 <= data.len() {
+            Some(entropy(code_point_counts.values().copied()))
+        } else {
+            None
+        };
+
+        DataStats {
+            len: data.len(),
+            byte_entropy: entropy(byte_counts.iter().copied()),
+            distinct_bytes: byte_counts.iter().filter(|&&count| count > 0).count(),
+            num_runs,
+            run_entropy: entropy(run_counts.iter().copied()),
+            run_length_bits,
+            digram_entropy: entropy(digram_counts.iter().copied()),
+            distinct_digrams: digram_counts.iter().filter(|&&count| count > 0).count(),
+            code_point_entropy,
+            distinct_code_points: code_point_counts.len(),
+            match_ratio: match_ratio(data),
+        }
+    }
+}
+
+/// Order-0 entropy in bits of data with the given symbol counts.
+pub fn entropy(counts: impl Iterator<Item = usize> + Clone) -> f64 {
+    let total = counts.clone().sum::<usize>() as f64;
+    counts
+        .filter(|&count| count > 0)
+        .map(|count| count as f64 * (total / count as f64).log2())
+        .sum()
+}
+
+fn match_ratio(data: &[u8]) -> f64 {
+    if data.len() < MIN_MATCH_LEN {
+        return 0.0;
+    }
+
+    let mut last_seen = HashMap::new();
+    let mut matched = 0;
+    let mut x = 0;
+    while x + MIN_MATCH_LEN <= data.len() {
+        let key = &data[x..x + MIN_MATCH_LEN];
+        match last_seen.insert(key, x) {
+            Some(start) => {
+                let match_len = data[x..]
+                    .iter()
+                    .zip(data[start..].iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                matched += match_len;
+                x += match_len;
+            }
+            None => x += 1,
+        }
+    }
+    matched as f64 / data.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy([4, 4].iter().copied()), 8.0);
+        assert_eq!(entropy([0, 7, 0].iter().copied()), 0.0);
+        assert_eq!(entropy([1, 1, 2].iter().copied()), 6.0);
+    }
+
+    #[test]
+    fn test_data_stats() {
+        let stats = DataStats::new(b"aaaabbcd");
+        assert_eq!(stats.distinct_bytes, 4);
+        assert_eq!(stats.num_runs, 4);
+        assert_eq!(stats.run_length_bits, 3.0);
+        assert_eq!(stats.distinct_digrams, 3);
+        assert_eq!(stats.distinct_code_points, 4);
+
+        assert!(DataStats::new(b"\xff\xfe\xfd").code_point_entropy.is_none());
+        assert_eq!(DataStats::new(b"abcd").match_ratio, 0.0);
+        assert!(DataStats::new(&b"abcdefgh".repeat(100)).match_ratio > 0.9);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::codec::Codec;
+use crate::stats::DataStats;
+
+/// Stores the data as it is. Used for data that the other codecs would expand.
+pub struct StoredCodec;
+
+impl Codec for StoredCodec {
+    fn name(&self) -> &'static str {
+        "stored"
+    }
+
+    fn id(&self) -> u8 {
+        7
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        data.to_vec()
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        data.to_vec()
+    }
+
+    fn estimate_size(&self, stats: &DataStats) -> Option<usize> {
+        Some(stats.len)
+    }
+}
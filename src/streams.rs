@@ -0,0 +1,42 @@
+use crate::varint::{read_varint, write_varint};
+
+/// Joins several byte streams into one. Every stream is prefixed with its length
+/// so that the streams can be separated again with `split_streams`.
+pub fn join_streams(streams: &[Vec<u8>]) -> Vec<u8> {
+    let mut joined = Vec::new();
+    for stream in streams {
+        write_varint(stream.len() as u64, &mut joined);
+        joined.extend_from_slice(stream);
+    }
+    joined
+}
+
+/// Splits data created with `join_streams` back into the original streams.
+pub fn split_streams(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut streams = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let len = read_varint(data, &mut pos) as usize;
+        let stream = data
+            .get(pos..pos + len)
+            .expect("Stream is longer than the remaining data");
+        streams.push(stream.to_vec());
+        pos += len;
+    }
+    streams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_split_streams() {
+        let streams = vec![vec![], vec![1, 2, 3], vec![7; 300], vec![0; 20_000], vec![]];
+        let joined = join_streams(&streams);
+
+        assert_eq!(joined.len(), 8 + 3 + 300 + 20_000);
+        assert_eq!(split_streams(&joined), streams);
+        assert!(split_streams(&[]).is_empty());
+    }
+}
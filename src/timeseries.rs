@@ -0,0 +1,273 @@
+use bitvec::{order::Lsb0, vec::BitVec};
+
+use crate::savebits::{load_bits_with_width, save_bits_with_width, SaveBits};
+
+/// A series of floats, such as the values of a metric. The series is saved like in
+/// Facebook's Gorilla: every value is XORed with the previous one, and only the bits
+/// between the leading and trailing zeros of the result are saved. Slowly changing
+/// values share their sign, exponent and high mantissa bits, so this takes far fewer
+/// bits than saving the values as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatSeries<F>(pub Vec<F>);
+
+/// A series of timestamps. Every timestamp is saved as the difference between its
+/// delta and the previous delta, which is zero for regularly spaced timestamps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampSeries(pub Vec<i64>);
+
+/// Converts a series to bytes. The end of the last byte is padded with zeros.
+pub fn series_to_bytes<S: SaveBits>(series: &S) -> Vec<u8> {
+    let mut output: BitVec<Lsb0, u8> = BitVec::new();
+    output.extend(series.save_bits());
+    output.into_vec()
+}
+
+pub fn series_from_bytes<S: SaveBits>(data: &[u8]) -> S {
+    let bits: BitVec<Lsb0, u8> = BitVec::from_vec(data.to_vec());
+    S::from_bits(&mut bits.iter().map(|r| *r))
+}
+
+/// Implements `SaveBits` for a series of floats. Every value after the first one is
+/// saved as one of the following:
+/// - `0` if the value is the same as the previous one
+/// - `10` and the meaningful bits of the XOR, if they fit in the previous window
+/// - `11`, the number of leading zeros, the number of meaningful bits minus one
+///   and the meaningful bits of the XOR
+macro_rules! impl_save_bits_for_float_series {
+    ($($t:ty: $bits:ty),*) => {
+        $(
+            impl SaveBits for FloatSeries<$t> {
+                fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+                    const WIDTH: u32 = <$bits>::BITS;
+                    const FIELD_WIDTH: u32 = WIDTH.trailing_zeros();
+
+                    let mut output: Vec<bool> = (self.0.len() + 1).save_bits().collect();
+                    let mut values = self.0.iter().map(|value| value.to_bits());
+                    let mut previous = match values.next() {
+                        Some(first) => first,
+                        None => return Box::new(output.into_iter()),
+                    };
+                    output.extend(previous.save_bits());
+
+                    // leading and trailing zeros of the previous window
+                    let mut window = None;
+                    for value in values {
+                        let xor = value ^ previous;
+                        previous = value;
+                        if xor == 0 {
+                            output.push(false);
+                            continue;
+                        }
+                        output.push(true);
+
+                        let leading = xor.leading_zeros();
+                        let trailing = xor.trailing_zeros();
+                        match window {
+                            Some((window_leading, window_trailing))
+                                if leading >= window_leading && trailing >= window_trailing =>
+                            {
+                                output.push(false);
+                                let meaningful = WIDTH - window_leading - window_trailing;
+                                let bits = (xor >> window_trailing) as u64;
+                                output.extend(save_bits_with_width(bits, meaningful));
+                            }
+                            _ => {
+                                let meaningful = WIDTH - leading - trailing;
+                                let stored_len = meaningful as u64 - 1;
+                                let bits = (xor >> trailing) as u64;
+                                output.push(true);
+                                output.extend(save_bits_with_width(leading as u64, FIELD_WIDTH));
+                                output.extend(save_bits_with_width(stored_len, FIELD_WIDTH));
+                                output.extend(save_bits_with_width(bits, meaningful));
+                                window = Some((leading, trailing));
+                            }
+                        }
+                    }
+
+                    Box::new(output.into_iter())
+                }
+
+                fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+                    const WIDTH: u32 = <$bits>::BITS;
+                    const FIELD_WIDTH: u32 = WIDTH.trailing_zeros();
+
+                    let len = usize::from_bits(iter) - 1;
+                    let mut values = Vec::with_capacity(len);
+                    if len == 0 {
+                        return FloatSeries(values);
+                    }
+                    let mut previous = <$bits>::from_bits(iter);
+                    values.push(<$t>::from_bits(previous));
+
+                    let mut window = (0, 0);
+                    for _ in 1..len {
+                        if load_bits_with_width(iter, 1) == 1 {
+                            if load_bits_with_width(iter, 1) == 1 {
+                                let leading = load_bits_with_width(iter, FIELD_WIDTH) as u32;
+                                let meaningful =
+                                    load_bits_with_width(iter, FIELD_WIDTH) as u32 + 1;
+                                window = (leading, WIDTH - leading - meaningful);
+                            }
+                            let (leading, trailing) = window;
+                            let meaningful = WIDTH - leading - trailing;
+                            let xor = load_bits_with_width(iter, meaningful) as $bits;
+                            previous ^= xor << trailing;
+                        }
+                        values.push(<$t>::from_bits(previous));
+                    }
+
+                    FloatSeries(values)
+                }
+            }
+        )*
+    };
+}
+
+impl_save_bits_for_float_series!(f32: u32, f64: u64);
+
+/// Widths of the buckets that a nonzero delta of deltas is saved in. A value in the
+/// bucket with index `x` is prefixed by `x + 1` ones and a zero. Values that do not
+/// fit in any bucket are prefixed by ones only and saved in full. A zero is saved as
+/// a single zero bit.
+const TIMESTAMP_BUCKETS: [u32; 3] = [7, 9, 12];
+
+impl SaveBits for TimestampSeries {
+    fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+        let mut output: Vec<bool> = (self.0.len() + 1).save_bits().collect();
+        let mut previous = match self.0.first() {
+            Some(first) => *first,
+            None => return Box::new(output.into_iter()),
+        };
+        output.extend((previous as u64).save_bits());
+
+        let mut previous_delta = 0i64;
+        for timestamp in self.0.iter().skip(1) {
+            let delta = timestamp.wrapping_sub(previous);
+            let delta_of_delta = delta.wrapping_sub(previous_delta);
+            previous = *timestamp;
+            previous_delta = delta;
+
+            if delta_of_delta == 0 {
+                output.push(false);
+                continue;
+            }
+            let bucket = TIMESTAMP_BUCKETS.iter().position(|width| {
+                let half = 1 << (width - 1);
+                -(half - 1) <= delta_of_delta && delta_of_delta <= half
+            });
+            match bucket {
+                Some(x) => {
+                    let width = TIMESTAMP_BUCKETS[x];
+                    output.extend((0..=x).map(|_| true));
+                    output.push(false);
+                    let offset = (1i64 << (width - 1)) - 1;
+                    output.extend(save_bits_with_width(
+                        (delta_of_delta + offset) as u64,
+                        width,
+                    ));
+                }
+                None => {
+                    output.extend(TIMESTAMP_BUCKETS.iter().map(|_| true));
+                    output.push(true);
+                    output.extend((delta_of_delta as u64).save_bits());
+                }
+            }
+        }
+
+        Box::new(output.into_iter())
+    }
+
+    fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+        let len = usize::from_bits(iter) - 1;
+        let mut timestamps = Vec::with_capacity(len);
+        if len == 0 {
+            return TimestampSeries(timestamps);
+        }
+        let mut previous = u64::from_bits(iter) as i64;
+        timestamps.push(previous);
+
+        let mut previous_delta = 0i64;
+        for _ in 1..len {
+            let mut num_ones = 0;
+            while num_ones <= TIMESTAMP_BUCKETS.len() && load_bits_with_width(iter, 1) == 1 {
+                num_ones += 1;
+            }
+            let delta_of_delta = match num_ones {
+                0 => 0,
+                x if x <= TIMESTAMP_BUCKETS.len() => {
+                    let width = TIMESTAMP_BUCKETS[x - 1];
+                    let offset = (1i64 << (width - 1)) - 1;
+                    load_bits_with_width(iter, width) as i64 - offset
+                }
+                _ => u64::from_bits(iter) as i64,
+            };
+            previous_delta = previous_delta.wrapping_add(delta_of_delta);
+            previous = previous.wrapping_add(previous_delta);
+            timestamps.push(previous);
+        }
+
+        TimestampSeries(timestamps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_series() {
+        let values: Vec<f64> = (0..1000)
+            .map(|x| 20.0 + (x / 10) as f64 * 0.25)
+            .chain(vec![
+                0.0,
+                -0.0,
+                f64::NAN,
+                f64::INFINITY,
+                f64::MIN_POSITIVE,
+                1e300,
+            ])
+            .collect();
+        let series = FloatSeries(values.clone());
+        let bits = series.save_bits().count();
+        assert!(bits < values.len() * 64 / 4);
+
+        let loaded = FloatSeries::<f64>::from_bits(&mut series.save_bits()).0;
+        assert_eq!(loaded.len(), values.len());
+        for (loaded, value) in loaded.iter().zip(values.iter()) {
+            assert_eq!(loaded.to_bits(), value.to_bits());
+        }
+
+        let values: Vec<f32> = (0..500).map(|x| (x as f32 * 0.01).sin()).collect();
+        let bytes = series_to_bytes(&FloatSeries(values.clone()));
+        assert_eq!(series_from_bytes::<FloatSeries<f32>>(&bytes).0, values);
+    }
+
+    #[test]
+    fn test_constant_float_series() {
+        let series = FloatSeries(vec![1.5f32; 100]);
+        assert_eq!(series.save_bits().count(), 13 + 32 + 99);
+        assert_eq!(
+            FloatSeries::<f32>::from_bits(&mut series.save_bits()),
+            series
+        );
+        assert!(
+            series_from_bytes::<FloatSeries<f64>>(&series_to_bytes(&FloatSeries::<f64>(vec![])))
+                .0
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_timestamp_series() {
+        let regular: Vec<i64> = (0..1000).map(|x| 1_600_000_000 + 60 * x).collect();
+        let series = TimestampSeries(regular);
+        // the first delta takes the smallest bucket, the rest take a bit each
+        assert_eq!(series.save_bits().count(), 19 + 64 + 2 + 7 + 998);
+
+        let timestamps = vec![5, 65, 125, 186, 240, 1000, 3000, -1, i64::MAX, i64::MIN, 0];
+        let series = TimestampSeries(timestamps);
+        assert_eq!(TimestampSeries::from_bits(&mut series.save_bits()), series);
+        let bytes = series_to_bytes(&series);
+        assert_eq!(series_from_bytes::<TimestampSeries>(&bytes), series);
+    }
+}
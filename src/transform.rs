@@ -0,0 +1,171 @@
+mod bcj;
+mod bwt;
+mod delta;
+mod distance;
+mod mtf;
+mod shuffle;
+mod xor;
+
+pub use self::bcj::{arm64_decode, arm64_encode, x86_decode, x86_encode, Arm64, X86};
+pub use self::bwt::{bwt_decode, bwt_encode, Bwt};
+pub use self::delta::{
+    delta_decode, delta_encode, int_delta_decode, int_delta_encode, Delta, IntDelta,
+};
+pub use self::distance::{distance_decode, distance_encode, DistanceCoding};
+pub use self::mtf::{mtf_decode, mtf_encode, Mtf};
+pub use self::shuffle::{
+    bit_shuffle_decode, bit_shuffle_encode, shuffle_decode, shuffle_encode, BitShuffle, Shuffle,
+};
+pub use self::xor::{xor_decode, xor_encode, Xor};
+
+/// A reversible transform that can be applied to the data before it is compressed.
+/// The ids and parameters of the applied transforms are stored in the compressed
+/// data, so that decompressing can apply their inverses in reverse order.
+pub trait Transform {
+    /// The name of the transform in pipeline specifications.
+    fn name(&self) -> &'static str;
+    /// The id of the transform in compressed data.
+    fn id(&self) -> u8;
+    /// The parameter of the transform, such as the width of the values it works on.
+    fn parameter(&self) -> u8 {
+        0
+    }
+    /// Creates the same kind of transform with another parameter. Returns `None` if the
+    /// transform does not take a parameter or if the parameter is invalid.
+    fn with_parameter(&self, _parameter: u8) -> Option<Box<dyn Transform>> {
+        None
+    }
+    fn forward(&self, data: &[u8]) -> Vec<u8>;
+    fn inverse(&self, data: &[u8]) -> Vec<u8>;
+}
+
+/// Returns all of the available transforms with their default parameters.
+pub fn transforms() -> Vec<Box<dyn Transform>> {
+    vec![
+        Box::new(Mtf),
+        Box::new(DistanceCoding),
+        Box::new(Delta::new(1).unwrap()),
+        Box::new(Bwt),
+        Box::new(IntDelta::new(2).unwrap()),
+        Box::new(Xor::new(2).unwrap()),
+        Box::new(X86),
+        Box::new(Arm64),
+        Box::new(Shuffle::new(8).unwrap()),
+        Box::new(BitShuffle::new(8).unwrap()),
+    ]
+}
+
+/// Creates a transform from its name. The name can be followed by a colon and
+/// a parameter, such as `delta:4`.
+pub fn transform_from_name(spec: &str) -> Option<Box<dyn Transform>> {
+    let (name, parameter) = match spec.find(':') {
+        Some(x) => (&spec[..x], Some(spec[x + 1..].parse().ok()?)),
+        None => (spec, None),
+    };
+    let transform = transforms()
+        .into_iter()
+        .find(|transform| transform.name() == name)?;
+    match parameter {
+        Some(parameter) => transform.with_parameter(parameter),
+        None => Some(transform),
+    }
+}
+
+pub fn transform_from_id(id: u8, parameter: u8) -> Option<Box<dyn Transform>> {
+    let transform = transforms()
+        .into_iter()
+        .find(|transform| transform.id() == id)?;
+    if transform.parameter() == parameter {
+        Some(transform)
+    } else {
+        transform.with_parameter(parameter)
+    }
+}
+
+/// Returns the name of the transform in the format accepted by `transform_from_name`.
+/// The parameter is only included if it is not the default one.
+pub fn transform_spec(transform: &dyn Transform) -> String {
+    let default = transforms()
+        .into_iter()
+        .find(|default| default.id() == transform.id());
+    match default {
+        Some(default) if default.parameter() == transform.parameter() => {
+            transform.name().to_string()
+        }
+        _ => format!("{}:{}", transform.name(), transform.parameter()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_inverse() {
+        let all_bytes: Vec<u8> = (0..=255).rev().collect();
+        let inputs = [
+            Vec::new(),
+            b"a".to_vec(),
+            b"banana bandana".to_vec(),
+            all_bytes.repeat(3),
+            [vec![1; 1000], vec![2; 300], vec![1; 10]].concat(),
+        ];
+
+        for transform in transforms() {
+            for input in inputs.iter() {
+                assert_eq!(&transform.inverse(&transform.forward(input)), input);
+            }
+        }
+        for spec in [
+            "delta:3",
+            "idelta:4",
+            "idelta:8",
+            "xor:4",
+            "shuffle:2",
+            "bitshuffle:1",
+            "bitshuffle:4",
+        ]
+        .iter()
+        {
+            let transform = transform_from_name(spec).unwrap();
+            for input in inputs.iter() {
+                assert_eq!(&transform.inverse(&transform.forward(input)), input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transform_names_and_ids() {
+        for (x, transform) in transforms().iter().enumerate() {
+            assert_eq!(
+                transform_from_name(transform.name()).unwrap().id(),
+                transform.id()
+            );
+            assert_eq!(
+                transform_from_id(transform.id(), transform.parameter())
+                    .unwrap()
+                    .name(),
+                transform.name()
+            );
+            assert_eq!(transform_spec(transform.as_ref()), transform.name());
+            for other in transforms().iter().skip(x + 1) {
+                assert_ne!(transform.id(), other.id());
+            }
+        }
+        assert!(transform_from_name("unknown").is_none());
+    }
+
+    #[test]
+    fn test_transform_parameters() {
+        let transform = transform_from_name("delta:4").unwrap();
+        assert_eq!(transform.parameter(), 4);
+        assert_eq!(transform_spec(transform.as_ref()), "delta:4");
+        assert_eq!(transform_from_id(transform.id(), 4).unwrap().parameter(), 4);
+
+        assert_eq!(transform_from_name("idelta").unwrap().parameter(), 2);
+        assert!(transform_from_name("idelta:9").is_none());
+        assert!(transform_from_name("delta:0").is_none());
+        assert!(transform_from_name("delta:x").is_none());
+        assert!(transform_from_name("mtf:2").is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use super::Transform;
+
+/// Branch conversion for x86 and x86-64 machine code. The relative targets of
+/// `call` (E8) and `jmp` (E9) instructions are turned into absolute ones, so that
+/// calls to the same function get the same bytes wherever they are.
+pub struct X86;
+
+impl Transform for X86 {
+    fn name(&self) -> &'static str {
+        "x86"
+    }
+
+    fn id(&self) -> u8 {
+        6
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        x86_encode(data)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        x86_decode(data)
+    }
+}
+
+/// Branch conversion for AArch64 machine code. The relative targets of `bl`
+/// instructions are turned into absolute ones.
+pub struct Arm64;
+
+impl Transform for Arm64 {
+    fn name(&self) -> &'static str {
+        "arm64"
+    }
+
+    fn id(&self) -> u8 {
+        7
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        arm64_encode(data)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        arm64_decode(data)
+    }
+}
+
+/// Targets of x86 branches are converted within this many bits, so that a converted
+/// operand is in the same range as the original one.
+const X86_TARGET_BITS: u32 = 25;
+
+/// Converts the four byte operands of E8 and E9 bytes that are within 16 MiB of
+/// the position. Operands that are out of the range are left as they are. The
+/// operand after every E8 and E9 byte is skipped whether it was converted or not,
+/// so the opcodes are never inside a converted operand and decoding finds the same
+/// ones.
+pub fn x86_encode(data: &[u8]) -> Vec<u8> {
+    x86_convert(data, |operand, pos| operand.wrapping_add(pos))
+}
+
+pub fn x86_decode(data: &[u8]) -> Vec<u8> {
+    x86_convert(data, |operand, pos| operand.wrapping_sub(pos))
+}
+
+fn x86_convert(data: &[u8], convert: impl Fn(u32, u32) -> u32) -> Vec<u8> {
+    let mut output = data.to_vec();
+    let mut pos = 0;
+    while pos + 5 <= output.len() {
+        let operand_bytes = [
+            output[pos + 1],
+            output[pos + 2],
+            output[pos + 3],
+            output[pos + 4],
+        ];
+        let operand = u32::from_le_bytes(operand_bytes);
+        if output[pos] == 0xe8 || output[pos] == 0xe9 {
+            if is_x86_target(operand) {
+                // the target is relative to the end of the instruction
+                let converted = sign_extend(convert(operand, pos as u32 + 5), X86_TARGET_BITS);
+                output[pos + 1..pos + 5].copy_from_slice(&converted.to_le_bytes());
+            }
+            pos += 5;
+        } else {
+            pos += 1;
+        }
+    }
+    output
+}
+
+/// Whether the highest byte of the operand is the sign of the lower bytes, which is
+/// the case for branches within the same program.
+fn is_x86_target(operand: u32) -> bool {
+    sign_extend(operand, X86_TARGET_BITS) == operand
+}
+
+fn sign_extend(value: u32, bits: u32) -> u32 {
+    let shift = u32::BITS - bits;
+    ((value << shift) as i32 >> shift) as u32
+}
+
+const ARM64_BL_MASK: u32 = 0xfc00_0000;
+const ARM64_BL_OPCODE: u32 = 0x9400_0000;
+
+/// Converts the 26-bit word offsets of `bl` instructions, which are aligned to four
+/// bytes, to word addresses.
+pub fn arm64_encode(data: &[u8]) -> Vec<u8> {
+    arm64_convert(data, |offset, pos| offset.wrapping_add(pos))
+}
+
+pub fn arm64_decode(data: &[u8]) -> Vec<u8> {
+    arm64_convert(data, |offset, pos| offset.wrapping_sub(pos))
+}
+
+fn arm64_convert(data: &[u8], convert: impl Fn(u32, u32) -> u32) -> Vec<u8> {
+    let mut output = data.to_vec();
+    for (x, instruction) in output.chunks_exact_mut(4).enumerate() {
+        let word = u32::from_le_bytes([
+            instruction[0],
+            instruction[1],
+            instruction[2],
+            instruction[3],
+        ]);
+        if word & ARM64_BL_MASK == ARM64_BL_OPCODE {
+            let offset = convert(word & !ARM64_BL_MASK, x as u32) & !ARM64_BL_MASK;
+            instruction.copy_from_slice(&(ARM64_BL_OPCODE | offset).to_le_bytes());
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_x86_encode() {
+        // two calls to the same function at 0x40 and a call out of the range
+        let mut code = vec![0x90; 0x80];
+        code[0x10..0x15].copy_from_slice(&[0xe8, 0x2b, 0, 0, 0]);
+        code[0x20..0x25].copy_from_slice(&[0xe8, 0x1b, 0, 0, 0]);
+        code[0x30..0x35].copy_from_slice(&[0xe9, 0xf0, 0xff, 0xff, 0xff]);
+        code[0x50..0x55].copy_from_slice(&[0xe8, 0, 0, 0, 0x12]);
+        code[0x7d..0x80].copy_from_slice(&[0xe8, 0, 0]);
+
+        let encoded = x86_encode(&code);
+        assert_eq!(encoded[0x11..0x15], [0x40, 0, 0, 0]);
+        assert_eq!(encoded[0x21..0x25], [0x40, 0, 0, 0]);
+        assert_eq!(encoded[0x31..0x35], [0x25, 0, 0, 0]);
+        assert_eq!(encoded[0x50..], code[0x50..]);
+        assert_eq!(x86_decode(&encoded), code);
+    }
+
+    #[test]
+    fn test_x86_wraps_within_range() {
+        // a forward branch near the end of the range stays in the range
+        let code = [0x90, 0x90, 0xe8, 0xff, 0xff, 0xff, 0x00];
+        let encoded = x86_encode(&code);
+        assert!(is_x86_target(u32::from_le_bytes([
+            encoded[3], encoded[4], encoded[5], encoded[6]
+        ])));
+        assert_eq!(x86_decode(&encoded), code);
+    }
+
+    #[test]
+    fn test_x86_skips_unconverted_operands() {
+        // the first operand is out of the range and holds an E8 with an operand that
+        // is in the range
+        let code = [0xe8, 0x00, 0xe8, 0xff, 0xfe, 0x00, 0x00];
+        let encoded = x86_encode(&code);
+        assert_eq!(encoded, code);
+        assert_eq!(x86_decode(&encoded), code);
+
+        // random bytes with many overlapping opcodes
+        let mut state = 12345u32;
+        let code: Vec<u8> = (0..10_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                [0xe8, 0xe9, 0x00, 0xff, (state >> 8) as u8][(state >> 16) as usize % 5]
+            })
+            .collect();
+        assert_eq!(x86_decode(&x86_encode(&code)), code);
+    }
+
+    #[test]
+    fn test_arm64_encode() {
+        // bl to the same function from the second and the fourth instruction
+        let mut code = Vec::new();
+        for word in [0xd503201f, 0x94000007, 0xd503201f, 0x94000005, 0x97ffffff].iter() {
+            code.extend(&u32::to_le_bytes(*word));
+        }
+        code.extend(&[0x94, 0x00]);
+
+        let encoded = arm64_encode(&code);
+        assert_eq!(encoded[4..8], 0x94000008u32.to_le_bytes());
+        assert_eq!(encoded[12..16], 0x94000008u32.to_le_bytes());
+        assert_eq!(encoded[16..20], 0x94000003u32.to_le_bytes());
+        assert_eq!(encoded[20..], code[20..]);
+        assert_eq!(arm64_decode(&encoded), code);
+    }
+}
@@ -0,0 +1,148 @@
+use super::Transform;
+
+/// The Burrows-Wheeler transform.
+pub struct Bwt;
+
+impl Transform for Bwt {
+    fn name(&self) -> &'static str {
+        "bwt"
+    }
+
+    fn id(&self) -> u8 {
+        3
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        bwt_encode(data)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        bwt_decode(data)
+    }
+}
+
+/// Number of bytes transformed at a time. Sorting the rotations of a block takes
+/// O(n log^2 n) time, which limits the size of a block.
+const BWT_BLOCK_LEN: usize = 1 << 18;
+
+/// Applies the Burrows-Wheeler transform to every block of the data. Every block is
+/// written as the index of the original block among its sorted rotations, saved as a
+/// little-endian `u32`, followed by the last bytes of the sorted rotations.
+pub fn bwt_encode(data: &[u8]) -> Vec<u8> {
+    encode_blocks(data, BWT_BLOCK_LEN)
+}
+
+pub fn bwt_decode(data: &[u8]) -> Vec<u8> {
+    decode_blocks(data, BWT_BLOCK_LEN)
+}
+
+fn encode_blocks(data: &[u8], block_len: usize) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + 4 * (data.len() / block_len + 1));
+    for block in data.chunks(block_len) {
+        let rotations = sort_rotations(block);
+        let primary_idx = rotations.iter().position(|&start| start == 0).unwrap();
+
+        encoded.extend_from_slice(&(primary_idx as u32).to_le_bytes());
+        encoded.extend(
+            rotations
+                .iter()
+                .map(|&start| block[(start + block.len() - 1) % block.len()]),
+        );
+    }
+    encoded
+}
+
+fn decode_blocks(data: &[u8], block_len: usize) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(data.len());
+    for block in data.chunks(block_len + 4) {
+        assert!(block.len() > 4, "Burrows-Wheeler block is too short");
+        let (primary_idx, last_column) = block.split_at(4);
+        let primary_idx = u32::from_le_bytes([
+            primary_idx[0],
+            primary_idx[1],
+            primary_idx[2],
+            primary_idx[3],
+        ]) as usize;
+
+        // the position of every byte of the last column in the first column
+        let mut byte_starts = [0; 256];
+        let mut byte_counts = [0; 256];
+        for byte in last_column {
+            byte_counts[*byte as usize] += 1;
+        }
+        for x in 1..256 {
+            byte_starts[x] = byte_starts[x - 1] + byte_counts[x - 1];
+        }
+        let mut seen = [0; 256];
+        let last_to_first: Vec<usize> = last_column
+            .iter()
+            .map(|byte| {
+                let pos = byte_starts[*byte as usize] + seen[*byte as usize];
+                seen[*byte as usize] += 1;
+                pos
+            })
+            .collect();
+
+        // the block is recovered backwards, starting from the last byte of the original block
+        let start = decoded.len();
+        let mut row = primary_idx;
+        for _ in 0..last_column.len() {
+            decoded.push(last_column[row]);
+            row = last_to_first[row];
+        }
+        decoded[start..].reverse();
+    }
+    decoded
+}
+
+/// Returns the start positions of the rotations of the block in sorted order. The
+/// rotations are sorted by prefix doubling: after every round, the rotations are
+/// sorted by twice as many bytes as before.
+fn sort_rotations(block: &[u8]) -> Vec<usize> {
+    let len = block.len();
+    let mut ranks: Vec<usize> = block.iter().map(|byte| *byte as usize).collect();
+    let mut rotations: Vec<usize> = (0..len).collect();
+    let mut sorted_len = 1;
+    if len == 0 {
+        return rotations;
+    }
+
+    loop {
+        let key = |start: usize| (ranks[start], ranks[(start + sorted_len) % len]);
+        rotations.sort_by_key(|&start| key(start));
+
+        let mut new_ranks = vec![0; len];
+        for x in 1..len {
+            let is_new_rank = key(rotations[x]) != key(rotations[x - 1]);
+            new_ranks[rotations[x]] = new_ranks[rotations[x - 1]] + is_new_rank as usize;
+        }
+        let all_distinct = new_ranks[rotations[len - 1]] == len - 1;
+        ranks = new_ranks;
+
+        sorted_len *= 2;
+        if all_distinct || sorted_len >= len {
+            break;
+        }
+    }
+    rotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bwt_encode() {
+        assert_eq!(bwt_encode(b"banana"), b"\x03\0\0\0nnbaaa".to_vec());
+        assert_eq!(bwt_encode(b"aaaa"), b"\0\0\0\0aaaa".to_vec());
+    }
+
+    #[test]
+    fn test_bwt_multiple_blocks() {
+        let input: Vec<u8> = (0..2500).map(|x| (x * x % 251) as u8).collect();
+        let encoded = encode_blocks(&input, 1000);
+
+        assert_eq!(encoded.len(), input.len() + 3 * 4);
+        assert_eq!(decode_blocks(&encoded, 1000), input);
+    }
+}
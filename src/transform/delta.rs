@@ -0,0 +1,182 @@
+use super::Transform;
+
+/// Delta coding of bytes. Every byte is replaced with its difference to the byte
+/// `stride` positions earlier, which suits interleaved channels such as RGB pixels.
+pub struct Delta {
+    stride: u8,
+}
+
+impl Delta {
+    /// Returns `None` if the stride is zero.
+    pub fn new(stride: u8) -> Option<Delta> {
+        if stride == 0 {
+            None
+        } else {
+            Some(Delta { stride })
+        }
+    }
+}
+
+impl Transform for Delta {
+    fn name(&self) -> &'static str {
+        "delta"
+    }
+
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn parameter(&self) -> u8 {
+        self.stride
+    }
+
+    fn with_parameter(&self, parameter: u8) -> Option<Box<dyn Transform>> {
+        Some(Box::new(Delta::new(parameter)?))
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        delta_encode(data, self.stride as usize)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        delta_decode(data, self.stride as usize)
+    }
+}
+
+/// Delta coding of little-endian unsigned integers that are `width` bytes wide.
+pub struct IntDelta {
+    width: u8,
+}
+
+impl IntDelta {
+    /// Returns `None` unless the width is 1, 2, 4 or 8.
+    pub fn new(width: u8) -> Option<IntDelta> {
+        match width {
+            1 | 2 | 4 | 8 => Some(IntDelta { width }),
+            _ => None,
+        }
+    }
+}
+
+impl Transform for IntDelta {
+    fn name(&self) -> &'static str {
+        "idelta"
+    }
+
+    fn id(&self) -> u8 {
+        4
+    }
+
+    fn parameter(&self) -> u8 {
+        self.width
+    }
+
+    fn with_parameter(&self, parameter: u8) -> Option<Box<dyn Transform>> {
+        Some(Box::new(IntDelta::new(parameter)?))
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        int_delta_encode(data, self.width as usize)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        int_delta_decode(data, self.width as usize)
+    }
+}
+
+/// Replaces every byte with its difference to the byte `stride` positions earlier.
+/// Slowly changing values therefore become small numbers. The first `stride` bytes
+/// are kept as they are.
+pub fn delta_encode(data: &[u8], stride: usize) -> Vec<u8> {
+    data.iter()
+        .enumerate()
+        .map(|(x, byte)| match x.checked_sub(stride) {
+            Some(previous) => byte.wrapping_sub(data[previous]),
+            None => *byte,
+        })
+        .collect()
+}
+
+pub fn delta_decode(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(data.len());
+    for (x, delta) in data.iter().enumerate() {
+        let byte = match x.checked_sub(stride) {
+            Some(previous) => delta.wrapping_add(output[previous]),
+            None => *delta,
+        };
+        output.push(byte);
+    }
+    output
+}
+
+/// Replaces every little-endian integer of `width` bytes with its difference to the
+/// previous integer, wrapping around on overflow. Bytes after the last whole integer
+/// are kept as they are.
+pub fn int_delta_encode(data: &[u8], width: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0u64;
+    let chunks = data.chunks_exact(width);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        let value = read_int(chunk);
+        output.extend_from_slice(&value.wrapping_sub(previous).to_le_bytes()[..width]);
+        previous = value;
+    }
+    output.extend_from_slice(remainder);
+    output
+}
+
+pub fn int_delta_decode(data: &[u8], width: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0u64;
+    let chunks = data.chunks_exact(width);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        previous = previous.wrapping_add(read_int(chunk));
+        output.extend_from_slice(&previous.to_le_bytes()[..width]);
+    }
+    output.extend_from_slice(remainder);
+    output
+}
+
+/// Reads a little-endian integer of at most 8 bytes.
+fn read_int(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_encode() {
+        assert_eq!(
+            delta_encode(&[1, 2, 3, 5, 4, 0], 1),
+            vec![1, 1, 1, 2, 255, 252]
+        );
+        assert_eq!(
+            delta_encode(&[10, 20, 11, 22, 12, 21], 2),
+            vec![10, 20, 1, 2, 1, 255]
+        );
+    }
+
+    #[test]
+    fn test_int_delta_encode() {
+        let samples: Vec<u8> = [1000u16, 1003, 999, 65535, 2]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .chain(Some(7))
+            .collect();
+        let expected: Vec<u8> = [1000u16, 3, 65532, 64536, 3]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .chain(Some(7))
+            .collect();
+
+        assert_eq!(int_delta_encode(&samples, 2), expected);
+        assert_eq!(int_delta_decode(&expected, 2), samples);
+    }
+}
@@ -0,0 +1,70 @@
+use super::Transform;
+use crate::varint::{read_varint, write_varint};
+
+/// Distance coding.
+pub struct DistanceCoding;
+
+impl Transform for DistanceCoding {
+    fn name(&self) -> &'static str {
+        "distance"
+    }
+
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        distance_encode(data)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        distance_decode(data)
+    }
+}
+
+/// Replaces every byte with the distance to the previous occurrence of the same byte.
+/// The first occurrence of a byte is written as a zero followed by the byte itself.
+/// The distances are written as varints.
+pub fn distance_encode(data: &[u8]) -> Vec<u8> {
+    let mut last_seen = [None; 256];
+    let mut encoded = Vec::with_capacity(data.len());
+    for (x, byte) in data.iter().enumerate() {
+        match last_seen[*byte as usize] {
+            Some(last_x) => write_varint((x - last_x) as u64, &mut encoded),
+            None => encoded.extend_from_slice(&[0, *byte]),
+        }
+        last_seen[*byte as usize] = Some(x);
+    }
+    encoded
+}
+
+pub fn distance_decode(data: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    while pos < data.len() {
+        let distance = read_varint(data, &mut pos) as usize;
+        let byte = if distance == 0 {
+            let byte = *data
+                .get(pos)
+                .expect("Distance coded data ended before a new byte");
+            pos += 1;
+            byte
+        } else {
+            decoded[decoded.len() - distance]
+        };
+        decoded.push(byte);
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_encode() {
+        assert_eq!(distance_encode(b"abab"), vec![0, b'a', 0, b'b', 2, 2]);
+        let far = [b"x".to_vec(), vec![b'y'; 200], b"x".to_vec()].concat();
+        assert_eq!(&distance_encode(&far)[203..], &[0xc9, 0x01]);
+    }
+}
@@ -0,0 +1,59 @@
+use super::Transform;
+
+/// Move-to-front coding.
+pub struct Mtf;
+
+impl Transform for Mtf {
+    fn name(&self) -> &'static str {
+        "mtf"
+    }
+
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        mtf_encode(data)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        mtf_decode(data)
+    }
+}
+
+/// Replaces every byte with its position in a list of all byte values, and then
+/// moves the byte to the front of the list. Recently seen bytes therefore become
+/// small numbers, and runs of the same byte become runs of zeros.
+pub fn mtf_encode(data: &[u8]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..=255).collect();
+    data.iter()
+        .map(|byte| {
+            let pos = order.iter().position(|b| b == byte).unwrap();
+            order[..=pos].rotate_right(1);
+            pos as u8
+        })
+        .collect()
+}
+
+pub fn mtf_decode(data: &[u8]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..=255).collect();
+    data.iter()
+        .map(|pos| {
+            let pos = *pos as usize;
+            let byte = order[pos];
+            order[..=pos].rotate_right(1);
+            byte
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mtf_encode() {
+        assert_eq!(mtf_encode(b"aaabbba"), vec![97, 0, 0, 98, 0, 0, 1]);
+        assert_eq!(mtf_encode(&[0, 1, 1, 0]), vec![0, 1, 0, 1]);
+    }
+}
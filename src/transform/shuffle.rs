@@ -0,0 +1,163 @@
+use super::Transform;
+
+/// Groups the bytes of values that are `width` bytes wide by their position in the
+/// value: first the lowest byte of every value, then the second byte and so on.
+/// The high bytes of numbers that are close to each other are often the same, so
+/// they end up in long runs.
+pub struct Shuffle {
+    width: u8,
+}
+
+impl Shuffle {
+    /// Returns `None` unless the width is 1, 2, 4 or 8.
+    pub fn new(width: u8) -> Option<Shuffle> {
+        match width {
+            1 | 2 | 4 | 8 => Some(Shuffle { width }),
+            _ => None,
+        }
+    }
+}
+
+impl Transform for Shuffle {
+    fn name(&self) -> &'static str {
+        "shuffle"
+    }
+
+    fn id(&self) -> u8 {
+        8
+    }
+
+    fn parameter(&self) -> u8 {
+        self.width
+    }
+
+    fn with_parameter(&self, parameter: u8) -> Option<Box<dyn Transform>> {
+        Some(Box::new(Shuffle::new(parameter)?))
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        shuffle_encode(data, self.width as usize)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        shuffle_decode(data, self.width as usize)
+    }
+}
+
+/// Like `Shuffle`, but groups the bits of the values instead of the bytes, so that
+/// every bit plane is stored on its own. Useful when only the lowest bits of the
+/// values change.
+pub struct BitShuffle {
+    width: u8,
+}
+
+impl BitShuffle {
+    /// Returns `None` unless the width is 1, 2, 4 or 8.
+    pub fn new(width: u8) -> Option<BitShuffle> {
+        match width {
+            1 | 2 | 4 | 8 => Some(BitShuffle { width }),
+            _ => None,
+        }
+    }
+}
+
+impl Transform for BitShuffle {
+    fn name(&self) -> &'static str {
+        "bitshuffle"
+    }
+
+    fn id(&self) -> u8 {
+        9
+    }
+
+    fn parameter(&self) -> u8 {
+        self.width
+    }
+
+    fn with_parameter(&self, parameter: u8) -> Option<Box<dyn Transform>> {
+        Some(Box::new(BitShuffle::new(parameter)?))
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        bit_shuffle_encode(data, self.width as usize)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        bit_shuffle_decode(data, self.width as usize)
+    }
+}
+
+/// Transposes the values into byte planes. Bytes after the last whole value are
+/// kept as they are.
+pub fn shuffle_encode(data: &[u8], width: usize) -> Vec<u8> {
+    let num_values = data.len() / width;
+    let mut output = Vec::with_capacity(data.len());
+    for byte in 0..width {
+        output.extend((0..num_values).map(|x| data[x * width + byte]));
+    }
+    output.extend_from_slice(&data[num_values * width..]);
+    output
+}
+
+pub fn shuffle_decode(data: &[u8], width: usize) -> Vec<u8> {
+    let num_values = data.len() / width;
+    let mut output = Vec::with_capacity(data.len());
+    for x in 0..num_values {
+        output.extend((0..width).map(|byte| data[byte * num_values + x]));
+    }
+    output.extend_from_slice(&data[num_values * width..]);
+    output
+}
+
+/// Transposes the values into bit planes, starting from the lowest bit. The bits are
+/// packed starting from the lowest bit of every byte. Bytes after the last whole
+/// value are kept as they are.
+pub fn bit_shuffle_encode(data: &[u8], width: usize) -> Vec<u8> {
+    let num_values = data.len() / width;
+    let mut output = vec![0; data.len()];
+    for x in 0..num_values {
+        for plane in 0..8 * width {
+            let bit = data[x * width + plane / 8] >> (plane % 8) & 1;
+            let pos = plane * num_values + x;
+            output[pos / 8] |= bit << (pos % 8);
+        }
+    }
+    output[num_values * width..].copy_from_slice(&data[num_values * width..]);
+    output
+}
+
+pub fn bit_shuffle_decode(data: &[u8], width: usize) -> Vec<u8> {
+    let num_values = data.len() / width;
+    let mut output = vec![0; data.len()];
+    for x in 0..num_values {
+        for plane in 0..8 * width {
+            let pos = plane * num_values + x;
+            let bit = data[pos / 8] >> (pos % 8) & 1;
+            output[x * width + plane / 8] |= bit << (plane % 8);
+        }
+    }
+    output[num_values * width..].copy_from_slice(&data[num_values * width..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shuffle_encode() {
+        let encoded = shuffle_encode(&[1, 2, 3, 4, 5, 6, 7], 3);
+        assert_eq!(encoded, [1, 4, 2, 5, 3, 6, 7]);
+        assert_eq!(shuffle_decode(&encoded, 3), [1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_bit_shuffle_encode() {
+        // the lowest bit plane of 0x0001 and 0x0003 holds two ones, and the second
+        // plane a zero and a one
+        let data = [0x01, 0x00, 0x03, 0x00, 0xff];
+        let encoded = bit_shuffle_encode(&data, 2);
+        assert_eq!(encoded, [0b1011, 0, 0, 0, 0xff]);
+        assert_eq!(bit_shuffle_decode(&encoded, 2), data);
+    }
+}
@@ -0,0 +1,86 @@
+use super::Transform;
+
+/// XOR of every little-endian value that is `width` bytes wide with the previous
+/// value. Unlike delta coding, this also leaves small numbers for values such as
+/// floats, whose neighbours tend to share the sign, exponent and high mantissa bits.
+pub struct Xor {
+    width: u8,
+}
+
+impl Xor {
+    /// Returns `None` unless the width is 1, 2, 4 or 8.
+    pub fn new(width: u8) -> Option<Xor> {
+        match width {
+            1 | 2 | 4 | 8 => Some(Xor { width }),
+            _ => None,
+        }
+    }
+}
+
+impl Transform for Xor {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn id(&self) -> u8 {
+        5
+    }
+
+    fn parameter(&self) -> u8 {
+        self.width
+    }
+
+    fn with_parameter(&self, parameter: u8) -> Option<Box<dyn Transform>> {
+        Some(Box::new(Xor::new(parameter)?))
+    }
+
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        xor_encode(data, self.width as usize)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Vec<u8> {
+        xor_decode(data, self.width as usize)
+    }
+}
+
+/// XORs every byte with the byte `width` positions earlier, which is the same as
+/// XORing every value with the previous one. The first value is kept as it is.
+pub fn xor_encode(data: &[u8], width: usize) -> Vec<u8> {
+    data.iter()
+        .enumerate()
+        .map(|(x, byte)| match x.checked_sub(width) {
+            Some(previous) => byte ^ data[previous],
+            None => *byte,
+        })
+        .collect()
+}
+
+pub fn xor_decode(data: &[u8], width: usize) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(data.len());
+    for (x, byte) in data.iter().enumerate() {
+        let value = match x.checked_sub(width) {
+            Some(previous) => byte ^ output[previous],
+            None => *byte,
+        };
+        output.push(value);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor_encode() {
+        let samples: Vec<u8> = [1.5f32, 1.25, 1.75]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let encoded = xor_encode(&samples, 4);
+
+        // neighbouring floats share their sign and exponent
+        assert_eq!(encoded[4..8], [0, 0, 0x60, 0]);
+        assert_eq!(xor_decode(&encoded, 4), samples);
+    }
+}
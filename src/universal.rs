@@ -0,0 +1,407 @@
+use crate::savebits::{bit_width, load_bits_with_width, save_bits_with_width};
+
+/// A code for integers that needs no table, since shorter codes are given to smaller
+/// numbers. Codes are written to and read from the same bit streams as `SaveBits`.
+pub trait UniversalCode {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>>;
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64;
+
+    /// Number of bits in the code of the value.
+    fn code_len(&self, value: u64) -> usize {
+        self.encode(value).count()
+    }
+}
+
+fn read_bit(iter: &mut dyn Iterator<Item = bool>, name: &str) -> bool {
+    iter.next()
+        .unwrap_or_else(|| panic!("Iterator returned None while loading {}", name))
+}
+
+/// Elias gamma code for positive integers: the binary representation of the value,
+/// preceded by one zero for every bit after the first one.
+pub struct EliasGamma;
+
+impl UniversalCode for EliasGamma {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>> {
+        assert!(value > 0, "Elias gamma code can not save zero");
+        let width = bit_width(value);
+        Box::new(
+            (1..width)
+                .map(|_| false)
+                .chain(save_bits_with_width(value, width)),
+        )
+    }
+
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64 {
+        let mut width = 1;
+        while !read_bit(iter, "an Elias gamma code") {
+            width += 1;
+        }
+        1 << (width - 1) | load_bits_with_width(iter, width - 1)
+    }
+
+    fn code_len(&self, value: u64) -> usize {
+        2 * bit_width(value) as usize - 1
+    }
+}
+
+/// Elias delta code for positive integers: the number of bits in the value saved
+/// with the gamma code, followed by the bits of the value after the first one.
+/// Shorter than the gamma code for values of 32 and above.
+pub struct EliasDelta;
+
+impl UniversalCode for EliasDelta {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>> {
+        assert!(value > 0, "Elias delta code can not save zero");
+        let width = bit_width(value);
+        Box::new(
+            EliasGamma
+                .encode(width as u64)
+                .chain(save_bits_with_width(value, width - 1)),
+        )
+    }
+
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64 {
+        let width = EliasGamma.decode(iter) as u32;
+        assert!(width <= u64::BITS, "Invalid Elias delta code");
+        1 << (width - 1) | load_bits_with_width(iter, width - 1)
+    }
+
+    fn code_len(&self, value: u64) -> usize {
+        let width = bit_width(value);
+        EliasGamma.code_len(width as u64) + width as usize - 1
+    }
+}
+
+/// Elias omega code for positive integers: groups of bits that each give the number
+/// of bits in the next group minus one, ending with the value itself and a zero.
+pub struct EliasOmega;
+
+impl UniversalCode for EliasOmega {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>> {
+        assert!(value > 0, "Elias omega code can not save zero");
+        let mut groups = Vec::new();
+        let mut n = value;
+        while n > 1 {
+            groups.push(n);
+            n = bit_width(n) as u64 - 1;
+        }
+
+        let mut output: Vec<bool> = groups
+            .iter()
+            .rev()
+            .flat_map(|group| save_bits_with_width(*group, bit_width(*group)))
+            .collect();
+        output.push(false);
+        Box::new(output.into_iter())
+    }
+
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64 {
+        let mut n = 1u64;
+        while read_bit(iter, "an Elias omega code") {
+            assert!(n < u64::BITS as u64, "Invalid Elias omega code");
+            n = 1 << n | load_bits_with_width(iter, n as u32);
+        }
+        n
+    }
+}
+
+/// Golomb code with the divisor `m`: the quotient in unary, as ones ended by a zero,
+/// followed by the remainder in truncated binary. With a power of two as the
+/// divisor, this is a Rice code. Golomb codes are optimal for geometrically
+/// distributed values, and can save zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Golomb {
+    m: u64,
+}
+
+impl Golomb {
+    pub fn new(m: u64) -> Golomb {
+        assert!(m > 0, "The divisor of a Golomb code must be positive");
+        Golomb { m }
+    }
+
+    /// A Rice code, which saves the lowest `k` bits of the value as they are.
+    pub fn rice(k: u32) -> Golomb {
+        Golomb::new(1 << k)
+    }
+
+    pub fn divisor(&self) -> u64 {
+        self.m
+    }
+
+    /// Selects the divisor that codes the values with the fewest bits. Every Rice
+    /// code is tried, as well as the divisor that is optimal for a geometric
+    /// distribution with the same mean as the values.
+    pub fn for_values(values: &[u64]) -> Golomb {
+        let total_len =
+            |code: &Golomb| -> u128 { values.iter().map(|x| code.code_len(*x) as u128).sum() };
+
+        let best_rice = (0..u64::BITS - 1)
+            .map(Golomb::rice)
+            .min_by_key(total_len)
+            .unwrap();
+        if values.is_empty() {
+            return best_rice;
+        }
+
+        let mean = values.iter().map(|x| *x as f64).sum::<f64>() / values.len() as f64;
+        let geometric = Golomb::new(((mean * std::f64::consts::LN_2).ceil() as u64).max(1));
+        if total_len(&geometric) < total_len(&best_rice) {
+            geometric
+        } else {
+            best_rice
+        }
+    }
+
+    /// Number of bits in the remainder `b`, and the number of remainders that take
+    /// one bit fewer.
+    fn remainder_bits(&self) -> (u32, u64) {
+        let b = bit_width(self.m - 1);
+        let short = ((1u128 << b) - self.m as u128) as u64;
+        (b, short)
+    }
+}
+
+impl UniversalCode for Golomb {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>> {
+        let quotient = value / self.m;
+        let remainder = value % self.m;
+        let (b, short) = self.remainder_bits();
+
+        let unary = (0..quotient).map(|_| true).chain(Some(false));
+        if remainder < short {
+            Box::new(unary.chain(save_bits_with_width(remainder, b - 1)))
+        } else {
+            Box::new(unary.chain(save_bits_with_width(remainder + short, b)))
+        }
+    }
+
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64 {
+        let mut quotient = 0;
+        while read_bit(iter, "a Golomb code") {
+            quotient += 1;
+        }
+        let (b, short) = self.remainder_bits();
+        if b == 0 {
+            return quotient;
+        }
+        let mut remainder = load_bits_with_width(iter, b - 1);
+        if remainder >= short {
+            remainder = (remainder << 1 | read_bit(iter, "a Golomb code") as u64) - short;
+        }
+        quotient * self.m + remainder
+    }
+
+    fn code_len(&self, value: u64) -> usize {
+        let (b, short) = self.remainder_bits();
+        let remainder_len = if value % self.m < short { b - 1 } else { b };
+        // the quotient of a large value with a small divisor does not fit in a usize
+        ((value / self.m) as usize).saturating_add(1 + remainder_len as usize)
+    }
+}
+
+/// Fibonacci code for positive integers: the Zeckendorf representation of the value,
+/// which never has two consecutive ones, starting from the smallest Fibonacci number
+/// and ended by an extra one. Robust against errors, since every code ends in `11`.
+pub struct Fibonacci;
+
+/// The Fibonacci numbers 1, 2, 3, 5, ... that fit in a `u64`.
+fn fibonacci_numbers() -> Vec<u64> {
+    let mut numbers = vec![1u64, 2];
+    while let Some(next) = numbers[numbers.len() - 2].checked_add(numbers[numbers.len() - 1]) {
+        numbers.push(next);
+    }
+    numbers
+}
+
+impl UniversalCode for Fibonacci {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>> {
+        assert!(value > 0, "Fibonacci code can not save zero");
+        let numbers = fibonacci_numbers();
+        let num_digits = numbers
+            .iter()
+            .take_while(|number| **number <= value)
+            .count();
+
+        let mut digits = vec![false; num_digits];
+        let mut rest = value;
+        for x in (0..num_digits).rev() {
+            if numbers[x] <= rest {
+                digits[x] = true;
+                rest -= numbers[x];
+            }
+        }
+        digits.push(true);
+        Box::new(digits.into_iter())
+    }
+
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64 {
+        let numbers = fibonacci_numbers();
+        let mut value = 0u64;
+        let mut previous_bit = false;
+        for number in numbers.iter().chain(Some(&0)) {
+            let bit = read_bit(iter, "a Fibonacci code");
+            if bit && previous_bit {
+                return value;
+            }
+            if bit {
+                value = value.checked_add(*number).expect("Invalid Fibonacci code");
+            }
+            previous_bit = bit;
+        }
+        panic!("Invalid Fibonacci code");
+    }
+}
+
+/// Exponential-Golomb code of order `k`: the value plus `2^k` saved with the gamma
+/// code, without the first `k` zeros. Can save zero. Order zero is used by H.264.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpGolomb {
+    pub k: u32,
+}
+
+impl UniversalCode for ExpGolomb {
+    fn encode(&self, value: u64) -> Box<dyn Iterator<Item = bool>> {
+        assert!(
+            self.k < u64::BITS,
+            "Order of an Exp-Golomb code is too large"
+        );
+        // the shifted value can take 65 bits
+        let shifted = value as u128 + (1u128 << self.k);
+        let width = u128::BITS - shifted.leading_zeros();
+        Box::new(
+            (self.k + 1..width)
+                .map(|_| false)
+                .chain((0..width).rev().map(move |x| (shifted >> x) & 1 == 1)),
+        )
+    }
+
+    fn decode(&self, iter: &mut dyn Iterator<Item = bool>) -> u64 {
+        let mut width = self.k + 1;
+        while !read_bit(iter, "an Exp-Golomb code") {
+            width += 1;
+        }
+        assert!(width <= u64::BITS + 1, "Invalid Exp-Golomb code");
+        let mut shifted = 1u128;
+        for _ in 1..width {
+            shifted = shifted << 1 | read_bit(iter, "an Exp-Golomb code") as u128;
+        }
+        (shifted - (1u128 << self.k)) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(code: &dyn UniversalCode, value: u64) -> String {
+        code.encode(value)
+            .map(|bit| if bit { '1' } else { '0' })
+            .collect()
+    }
+
+    fn test_values() -> Vec<u64> {
+        (1..300)
+            .chain((0..64).map(|x| 1 << x))
+            .chain((1..64).map(|x| (1 << x) - 1))
+            .chain((1..64).map(|x| (1 << x) + 1))
+            .chain(Some(u64::MAX))
+            .collect()
+    }
+
+    #[test]
+    fn test_code_words() {
+        assert_eq!(bits(&EliasGamma, 1), "1");
+        assert_eq!(bits(&EliasGamma, 13), "0001101");
+        assert_eq!(bits(&EliasDelta, 1), "1");
+        assert_eq!(bits(&EliasDelta, 13), "00100101");
+        assert_eq!(bits(&EliasOmega, 1), "0");
+        assert_eq!(bits(&EliasOmega, 2), "100");
+        assert_eq!(bits(&EliasOmega, 16), "10100100000");
+        assert_eq!(bits(&Fibonacci, 1), "11");
+        assert_eq!(bits(&Fibonacci, 4), "1011");
+        assert_eq!(bits(&Fibonacci, 12), "101011");
+        assert_eq!(bits(&Golomb::rice(2), 9), "11001");
+        assert_eq!(bits(&Golomb::new(10), 42), "11110010");
+        assert_eq!(bits(&Golomb::new(10), 5), "0101");
+        assert_eq!(bits(&Golomb::new(10), 7), "01101");
+        assert_eq!(bits(&ExpGolomb { k: 0 }, 0), "1");
+        assert_eq!(bits(&ExpGolomb { k: 0 }, 3), "00100");
+        assert_eq!(bits(&ExpGolomb { k: 2 }, 3), "111");
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let positive: Vec<Box<dyn UniversalCode>> = vec![
+            Box::new(EliasGamma),
+            Box::new(EliasDelta),
+            Box::new(EliasOmega),
+            Box::new(Fibonacci),
+        ];
+        let with_zero: Vec<Box<dyn UniversalCode>> = vec![
+            Box::new(ExpGolomb { k: 0 }),
+            Box::new(ExpGolomb { k: 5 }),
+            Box::new(ExpGolomb { k: 63 }),
+        ];
+        let values = test_values();
+
+        for code in positive.iter().chain(with_zero.iter()) {
+            let encoded: Vec<bool> = values.iter().flat_map(|x| code.encode(*x)).collect();
+            let mut it = encoded.iter().copied();
+            for value in values.iter() {
+                assert_eq!(code.decode(&mut it), *value);
+            }
+            assert!(it.next().is_none());
+            for value in values.iter().step_by(7) {
+                assert_eq!(code.code_len(*value), code.encode(*value).count());
+            }
+        }
+        for code in with_zero.iter() {
+            assert_eq!(code.decode(&mut code.encode(0)), 0);
+        }
+    }
+
+    #[test]
+    fn test_golomb() {
+        let values: Vec<u64> = (0..300).chain(Some(1 << 20)).collect();
+        for m in [1, 3, 7, 10, 64, 1000].iter() {
+            let code = Golomb::new(*m);
+            let encoded: Vec<bool> = values.iter().flat_map(|x| code.encode(*x)).collect();
+            let mut it = encoded.iter().copied();
+            for value in values.iter() {
+                assert_eq!(code.decode(&mut it), *value);
+                assert_eq!(code.code_len(*value), code.encode(*value).count());
+            }
+        }
+    }
+
+    #[test]
+    fn test_golomb_parameter_selection() {
+        let mut state = 12345u32;
+        let values: Vec<u64> = (0..1000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u64 % 200
+            })
+            .collect();
+        let selected = Golomb::for_values(&values);
+        let total_len = |code: Golomb| values.iter().map(|x| code.code_len(*x)).sum::<usize>();
+
+        for k in 0..20 {
+            assert!(total_len(selected) <= total_len(Golomb::rice(k)));
+        }
+        assert_eq!(Golomb::for_values(&[0; 10]).divisor(), 1);
+        assert_eq!(Golomb::for_values(&[]).divisor(), 1);
+
+        assert_eq!(Golomb::rice(0).code_len(u64::MAX), usize::MAX);
+        let selected = Golomb::for_values(&[u64::MAX]);
+        assert_eq!(selected.decode(&mut selected.encode(u64::MAX)), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_gamma_zero() {
+        let _ = EliasGamma.encode(0);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::codec::Codec;
+use crate::huffman::HuffmanCodec;
+use crate::savebits::{load_bits_with_width, save_bits_with_width, SaveBits};
+use crate::stats::DataStats;
+
+/// A symbol of UTF-8 text. Valid UTF-8 sequences are decoded into characters, and
+/// the bytes of invalid sequences are kept as they are.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum CodePoint {
+    Char(char),
+    Byte(u8),
+}
+
+/// Number of bits needed to save any Unicode scalar value.
+const CHAR_BITS: u32 = 21;
+
+impl SaveBits for CodePoint {
+    fn save_bits(&self) -> Box<dyn Iterator<Item = bool>> {
+        match *self {
+            CodePoint::Char(c) => {
+                Box::new(std::iter::once(false).chain(save_bits_with_width(c as u64, CHAR_BITS)))
+            }
+            CodePoint::Byte(byte) => Box::new(std::iter::once(true).chain(byte.save_bits())),
+        }
+    }
+
+    fn from_bits(iter: &mut dyn Iterator<Item = bool>) -> Self {
+        match iter.next() {
+            Some(false) => {
+                let s = load_bits_with_width(iter, CHAR_BITS) as u32;
+                CodePoint::Char(std::char::from_u32(s).expect("Loaded an invalid char"))
+            }
+            Some(true) => CodePoint::Byte(u8::from_bits(iter)),
+            None => panic!("Iterator returned None while loading CodePoint"),
+        }
+    }
+}
+
+/// Compresses the data as a sequence of Unicode code points rather than bytes,
+/// so that multi-byte characters are coded with a single code.
+pub fn compress_utf8(data: &[u8]) -> Vec<u8> {
+    HuffmanCodec::new().encode(&to_code_points(data))
+}
+
+pub fn decompress_utf8(data: &[u8]) -> Vec<u8> {
+    let mut decompressed = Vec::new();
+    let mut buf = [0; 4];
+    for code_point in HuffmanCodec::new().decode(data.to_vec()) {
+        match code_point {
+            CodePoint::Char(c) => {
+                decompressed.extend_from_slice(c.encode_utf8(&mut buf).as_bytes())
+            }
+            CodePoint::Byte(byte) => decompressed.push(byte),
+        }
+    }
+    decompressed
+}
+
+/// Decodes the data as UTF-8. Every byte of an invalid sequence becomes a `CodePoint::Byte`.
+fn to_code_points(mut data: &[u8]) -> Vec<CodePoint> {
+    let mut code_points = Vec::new();
+    while !data.is_empty() {
+        let (valid_len, invalid_len) = match std::str::from_utf8(data) {
+            Ok(_) => (data.len(), 0),
+            Err(e) => (
+                e.valid_up_to(),
+                e.error_len().unwrap_or(data.len() - e.valid_up_to()),
+            ),
+        };
+        let (valid, rest) = data.split_at(valid_len);
+        let (invalid, rest) = rest.split_at(invalid_len);
+
+        let text = std::str::from_utf8(valid).unwrap();
+        code_points.extend(text.chars().map(CodePoint::Char));
+        code_points.extend(invalid.iter().map(|&byte| CodePoint::Byte(byte)));
+        data = rest;
+    }
+    code_points
+}
+
+/// Codes the code points of UTF-8 text.
+pub struct Utf8Codec;
+
+impl Codec for Utf8Codec {
+    fn name(&self) -> &'static str {
+        "utf8"
+    }
+
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_utf8(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_utf8(data)
+    }
+
+    fn estimate_size(&self, stats: &DataStats) -> Option<usize> {
+        // every symbol takes about 23 bits in the tree
+        stats
+            .code_point_entropy
+            .map(|entropy| ((entropy + 23.0 * stats.distinct_code_points as f64) / 8.0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_code_points() {
+        assert_eq!(
+            to_code_points("aä語".as_bytes()),
+            vec![
+                CodePoint::Char('a'),
+                CodePoint::Char('ä'),
+                CodePoint::Char('語')
+            ]
+        );
+        assert_eq!(
+            to_code_points(b"a\xe8\xaa\xff\xc3\xa4\xe8"),
+            vec![
+                CodePoint::Char('a'),
+                CodePoint::Byte(0xe8),
+                CodePoint::Byte(0xaa),
+                CodePoint::Byte(0xff),
+                CodePoint::Char('ä'),
+                CodePoint::Byte(0xe8)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_load_code_point() {
+        for code_point in [
+            CodePoint::Char('\0'),
+            CodePoint::Char('語'),
+            CodePoint::Char(char::MAX),
+            CodePoint::Byte(0),
+            CodePoint::Byte(0xff),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                *code_point,
+                CodePoint::from_bits(&mut code_point.save_bits())
+            );
+        }
+    }
+
+    #[test]
+    fn test_compress_decompress_utf8() {
+        let inputs = [
+            Vec::new(),
+            "圧縮は大切です。圧縮は楽しいです。".repeat(20).into_bytes(),
+            b"mixed \xff\xfe bytes \xe3\x81 and text \xe3\x81\x82".to_vec(),
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(&decompress_utf8(&compress_utf8(input)), input);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+/// Writes the value as a LEB128 varint: seven bits at a time, starting from the
+/// least significant bits. The highest bit of a byte tells whether more bytes follow.
+pub fn write_varint(mut value: u64, output: &mut Vec<u8>) {
+    while value >= 0x80 {
+        output.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+/// Reads a varint written with `write_varint` starting from `pos`, and moves `pos`
+/// past it.
+pub fn read_varint(data: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).expect("Data ended while reading a varint");
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_read_varint() {
+        let mut output = Vec::new();
+        for value in [0, 127, 128, 300, u64::MAX].iter() {
+            write_varint(*value, &mut output);
+        }
+        assert_eq!(output[..6], [0, 127, 0x80, 1, 0xac, 2]);
+        assert_eq!(output.len(), 6 + 10);
+
+        let mut pos = 0;
+        for value in [0, 127, 128, 300, u64::MAX].iter() {
+            assert_eq!(read_varint(&output, &mut pos), *value);
+        }
+        assert_eq!(pos, output.len());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::codec::Codec;
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::intcodec::{zigzag_decode, zigzag_encode};
+use crate::savebits::{load_bits_with_width, save_bits_with_width, SaveBits};
+use crate::streams::{join_streams, split_streams};
+use crate::universal::{Golomb, UniversalCode};
+use bitvec::prelude::*;
+
+/// Number of frames that are predicted with the same predictor.
+const BLOCK_LEN: usize = 4096;
+
+/// The highest order of the fixed predictors.
+const MAX_ORDER: usize = 4;
+const ORDER_WIDTH: u32 = 3;
+
+/// Residuals are coded with quotients of at most this many bits, so that a single
+/// spike in a quiet block can not make the code of the block very long.
+const MAX_QUOTIENT_BITS: u32 = 16;
+
+/// The layout of the samples of a PCM WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Format {
+    /// The position of the first sample, after the header of the data chunk.
+    data_start: usize,
+    num_channels: usize,
+    /// The number of bytes in a sample of one channel.
+    sample_len: usize,
+    /// The length of the data chunk given in its header.
+    data_len: usize,
+}
+
+/// Parses the chunks of a RIFF WAVE file up to the start of the data chunk. Returns
+/// `None` if the data is not an integer PCM WAV file.
+fn parse_header(data: &[u8]) -> Option<Format> {
+    if data.get(..4)? != b"RIFF" || data.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let read_u16 = |pos: usize| Some(u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+    let read_u32 = |pos: usize| {
+        let bytes = data.get(pos..pos + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    let mut pos = 12;
+    let mut channels_and_sample_len = None;
+    loop {
+        let chunk_id = data.get(pos..pos + 4)?;
+        let chunk_len = read_u32(pos + 4)? as usize;
+        let body = pos + 8;
+        match chunk_id {
+            b"fmt " => {
+                // 1 is integer PCM and 0xfffe is the extensible format
+                let format = read_u16(body)?;
+                let num_channels = read_u16(body + 2)? as usize;
+                let block_align = read_u16(body + 12)? as usize;
+                let sample_bits = read_u16(body + 14)? as usize;
+                let is_valid = (format == 1 || format == 0xfffe)
+                    && num_channels > 0
+                    && [8, 16, 24, 32].contains(&sample_bits)
+                    && block_align == num_channels * sample_bits / 8;
+                if !is_valid {
+                    return None;
+                }
+                channels_and_sample_len = Some((num_channels, sample_bits / 8));
+            }
+            b"data" => {
+                let (num_channels, sample_len) = channels_and_sample_len?;
+                return Some(Format {
+                    data_start: body,
+                    num_channels,
+                    sample_len,
+                    data_len: chunk_len,
+                });
+            }
+            _ => {}
+        }
+        // chunks are padded to an even length
+        pos = body.checked_add(chunk_len + chunk_len % 2)?;
+    }
+}
+
+/// Reads a little-endian sample. Samples of 8 bits are unsigned and the others are
+/// signed.
+fn read_sample(bytes: &[u8]) -> i64 {
+    match bytes.len() {
+        1 => bytes[0] as i64,
+        _ => {
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0u64, |value, byte| value << 8 | *byte as u64);
+            let shift = 64 - 8 * bytes.len();
+            ((value << shift) as i64) >> shift
+        }
+    }
+}
+
+fn write_sample(sample: i64, sample_len: usize, output: &mut Vec<u8>) {
+    output.extend_from_slice(&sample.to_le_bytes()[..sample_len]);
+}
+
+/// Predicts the next sample from the previous ones with the fixed polynomial
+/// predictors of FLAC. Samples before the start of the channel are zero.
+fn predict(samples: &[i64], pos: usize, order: usize) -> i64 {
+    let previous = |x: usize| if pos >= x { samples[pos - x] } else { 0 };
+    match order {
+        0 => 0,
+        1 => previous(1),
+        2 => 2 * previous(1) - previous(2),
+        3 => 3 * previous(1) - 3 * previous(2) + previous(3),
+        _ => 4 * previous(1) - 6 * previous(2) + 4 * previous(3) - previous(4),
+    }
+}
+
+/// Compresses PCM WAV files losslessly. The samples of every channel are split into
+/// blocks, and every block is predicted with the fixed predictor that gives the
+/// smallest residuals. The residuals are coded with the Golomb-Rice code that suits
+/// them best. The header and any chunks after the samples are kept as they are.
+/// Other data is compressed with Huffman coding.
+pub fn compress_wav(data: &[u8]) -> Vec<u8> {
+    let format = match parse_header(data) {
+        Some(format) if format.data_start <= data.len() => format,
+        _ => return join_streams(&[Vec::new(), compress(data)]),
+    };
+
+    let frame_len = format.num_channels * format.sample_len;
+    let available_len = format.data_len.min(data.len() - format.data_start);
+    let num_frames = available_len / frame_len;
+    let samples_end = format.data_start + num_frames * frame_len;
+    let frames = data[format.data_start..samples_end].chunks(frame_len);
+    let mut channels = vec![Vec::with_capacity(num_frames); format.num_channels];
+    for frame in frames {
+        for (channel, sample) in channels.iter_mut().zip(frame.chunks(format.sample_len)) {
+            channel.push(read_sample(sample));
+        }
+    }
+
+    let mut output: BitVec<Lsb0, u8> = BitVec::new();
+    output.extend((num_frames + 1).save_bits());
+    for block_start in (0..num_frames).step_by(BLOCK_LEN) {
+        let block_end = (block_start + BLOCK_LEN).min(num_frames);
+        for channel in channels.iter() {
+            let residuals = |order| {
+                (block_start..block_end)
+                    .map(move |pos| zigzag_encode(channel[pos] - predict(channel, pos, order)))
+            };
+            let order = (0..=MAX_ORDER)
+                .min_by_key(|order| residuals(*order).map(|x| x as u128).sum::<u128>())
+                .unwrap();
+            let residuals: Vec<u64> = residuals(order).collect();
+
+            let max_residual = residuals.iter().copied().max().unwrap_or(0);
+            let mut code = Golomb::for_values(&residuals);
+            if (max_residual / code.divisor()) >> MAX_QUOTIENT_BITS > 0 {
+                let k = u64::BITS - (max_residual >> MAX_QUOTIENT_BITS).leading_zeros();
+                code = Golomb::rice(k);
+            }
+
+            output.extend(save_bits_with_width(order as u64, ORDER_WIDTH));
+            output.extend((code.divisor() as usize).save_bits());
+            for residual in residuals {
+                output.extend(code.encode(residual));
+            }
+        }
+    }
+
+    join_streams(&[
+        data[..format.data_start].to_vec(),
+        output.into_vec(),
+        compress(&data[samples_end..]),
+    ])
+}
+
+pub fn decompress_wav(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in WAV data");
+
+    let mut output = next_stream();
+    if output.is_empty() {
+        return decompress(next_stream());
+    }
+    let format = parse_header(&output).expect("Invalid WAV header");
+    let bits: BitVec<Lsb0, u8> = BitVec::from_vec(next_stream());
+    let mut iter = bits.iter().map(|bit| *bit);
+
+    let num_frames = usize::from_bits(&mut iter) - 1;
+    let mut channels = vec![Vec::with_capacity(num_frames); format.num_channels];
+    for block_start in (0..num_frames).step_by(BLOCK_LEN) {
+        let block_end = (block_start + BLOCK_LEN).min(num_frames);
+        for channel in channels.iter_mut() {
+            let order = load_bits_with_width(&mut iter, ORDER_WIDTH) as usize;
+            let code = Golomb::new(usize::from_bits(&mut iter) as u64);
+            for pos in block_start..block_end {
+                let residual = zigzag_decode(code.decode(&mut iter));
+                let sample = predict(channel, pos, order) + residual;
+                channel.push(sample);
+            }
+        }
+    }
+
+    for pos in 0..num_frames {
+        for channel in channels.iter() {
+            write_sample(channel[pos], format.sample_len, &mut output);
+        }
+    }
+    output.extend(decompress(next_stream()));
+    output
+}
+
+/// Codes PCM WAV files with linear prediction.
+pub struct WavCodec;
+
+impl Codec for WavCodec {
+    fn name(&self) -> &'static str {
+        "wav"
+    }
+
+    fn id(&self) -> u8 {
+        13
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_wav(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_wav(data)
+    }
+
+    fn matches_format(&self, data: &[u8]) -> bool {
+        parse_header(data).is_some_and(|format| format.data_start <= data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A WAV file with interleaved samples and a chunk after the samples.
+    fn wav(num_channels: usize, sample_len: usize, samples: &[i64]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend(&1u16.to_le_bytes());
+        fmt.extend(&(num_channels as u16).to_le_bytes());
+        fmt.extend(&44100u32.to_le_bytes());
+        fmt.extend(&((44100 * num_channels * sample_len) as u32).to_le_bytes());
+        fmt.extend(&((num_channels * sample_len) as u16).to_le_bytes());
+        fmt.extend(&((8 * sample_len) as u16).to_le_bytes());
+
+        let mut sample_bytes = Vec::new();
+        for sample in samples {
+            write_sample(*sample, sample_len, &mut sample_bytes);
+        }
+
+        let mut chunks = b"WAVE".to_vec();
+        for (id, body) in [
+            (b"fmt ", fmt),
+            (b"data", sample_bytes),
+            (b"LIST", b"odd".to_vec()),
+        ]
+        .iter()
+        {
+            chunks.extend(id.iter());
+            chunks.extend(&(body.len() as u32).to_le_bytes());
+            chunks.extend(body);
+            if body.len() % 2 == 1 {
+                chunks.push(0);
+            }
+        }
+        [&b"RIFF"[..], &(chunks.len() as u32).to_le_bytes(), &chunks].concat()
+    }
+
+    /// Two tones with a little noise.
+    fn tone(len: usize, amplitude: f64) -> Vec<i64> {
+        (0..len)
+            .map(|x| {
+                let t = x as f64 / 44100.0;
+                let value = (t * 440.0 * std::f64::consts::TAU).sin() * 0.7
+                    + (t * 1234.0 * std::f64::consts::TAU).sin() * 0.2;
+                (value * amplitude) as i64 + (x * 7919 % 13) as i64 - 6
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let file = wav(2, 2, &[1, -1, 2, -2]);
+        assert_eq!(
+            parse_header(&file),
+            Some(Format {
+                data_start: 44,
+                num_channels: 2,
+                sample_len: 2,
+                data_len: 8
+            })
+        );
+        assert_eq!(parse_header(&file[..44]), parse_header(&file));
+        assert!(parse_header(&file[..40]).is_none());
+        assert!(parse_header(b"RIFF\0\0\0\0WAVEdata\0\0\0\0").is_none());
+    }
+
+    #[test]
+    fn test_samples() {
+        for (bytes, sample) in [
+            (&[0x80][..], 128),
+            (&[0xff, 0x7f], 32767),
+            (&[0x00, 0x80], -32768),
+            (&[0xfe, 0xff, 0xff], -2),
+            (&[0x00, 0x00, 0x00, 0x80], i32::MIN as i64),
+        ]
+        .iter()
+        {
+            assert_eq!(read_sample(bytes), *sample);
+            let mut output = Vec::new();
+            write_sample(*sample, bytes.len(), &mut output);
+            assert_eq!(&output, bytes);
+        }
+    }
+
+    #[test]
+    fn test_compress_decompress_wav() {
+        let stereo: Vec<i64> = tone(10_000, 20_000.0)
+            .into_iter()
+            .flat_map(|sample| vec![sample, sample / 2])
+            .collect();
+        let stereo = wav(2, 2, &stereo);
+        let unsigned: Vec<i64> = tone(5000, 100.0).iter().map(|x| x + 128).collect();
+        let mut spike = tone(5000, 1_000_000.0);
+        spike[100] = i32::MAX as i64;
+        let inputs = [
+            b"".to_vec(),
+            b"RIFF but not a wave file".to_vec(),
+            wav(1, 2, &[]),
+            stereo[..stereo.len() - 13].to_vec(),
+            wav(1, 1, &unsigned),
+            wav(3, 3, &tone(3000, 8_000_000.0)),
+            wav(1, 4, &spike),
+            stereo.clone(),
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&decompress_wav(&compress_wav(input)), input);
+        }
+
+        assert!(compress_wav(&stereo).len() < compress(&stereo).len() * 2 / 3);
+    }
+
+    #[test]
+    fn test_matches_format() {
+        let mono = wav(1, 2, &tone(100, 1000.0));
+        assert!(WavCodec.matches_format(&mono));
+        assert!(WavCodec.matches_format(&mono[..mono.len() - 10]));
+        assert!(!WavCodec.matches_format(&mono[..20]));
+        assert!(!WavCodec.matches_format(&mono[100..]));
+    }
+}
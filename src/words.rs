@@ -0,0 +1,165 @@
+use crate::codec::Codec;
+use crate::compressor::compress;
+use crate::decompressor::decompress;
+use crate::huffman::HuffmanCodec;
+use crate::streams::{join_streams, split_streams};
+use std::collections::HashMap;
+
+/// Tokens are coded only if they are used at least this many times on average.
+/// Otherwise the vocabulary is about as large as the data, which is the case for
+/// binary data.
+const MIN_USES_PER_TOKEN: usize = 4;
+
+/// Compresses text by splitting it into words and the separators between them and
+/// coding the resulting token stream. The vocabulary is stored before the tokens,
+/// ordered from the most common token to the least common one. Data with too few
+/// repeated tokens is compressed with Huffman coding instead.
+pub fn compress_words(data: &[u8]) -> Vec<u8> {
+    let tokens = tokenize(data);
+
+    let mut num_uses = HashMap::new();
+    for token in tokens.iter() {
+        *num_uses.entry(*token).or_insert(0) += 1;
+    }
+    if num_uses.len() * MIN_USES_PER_TOKEN > tokens.len() {
+        return join_streams(&[Vec::new(), compress(data)]);
+    }
+    let mut vocabulary: Vec<(usize, &[u8])> = num_uses
+        .into_iter()
+        .map(|(token, count)| (count, token))
+        .collect();
+    // sort in descending order
+    vocabulary.sort_by(|a, b| b.cmp(a));
+
+    // indices start from one, since zero can not be saved
+    let indices: HashMap<&[u8], usize> = vocabulary
+        .iter()
+        .zip(1..)
+        .map(|((_, token), idx)| (*token, idx))
+        .collect();
+
+    let token_lens: Vec<usize> = vocabulary.iter().map(|(_, token)| token.len()).collect();
+    let vocabulary_bytes: Vec<u8> = vocabulary
+        .iter()
+        .flat_map(|(_, token)| token.iter().copied())
+        .collect();
+    let token_indices: Vec<usize> = tokens.iter().map(|token| indices[token]).collect();
+
+    let codec = HuffmanCodec::new();
+    join_streams(&[
+        codec.encode(&token_lens),
+        compress(&vocabulary_bytes),
+        codec.encode(&token_indices),
+    ])
+}
+
+pub fn decompress_words(data: &[u8]) -> Vec<u8> {
+    let mut streams = split_streams(data).into_iter();
+    let mut next_stream = || streams.next().expect("Missing stream in word data");
+
+    let token_lens = next_stream();
+    if token_lens.is_empty() {
+        return decompress(next_stream());
+    }
+    let codec = HuffmanCodec::<usize>::new();
+    let token_lens = codec.decode(token_lens);
+    let vocabulary_bytes = decompress(next_stream());
+    let token_indices = codec.decode(next_stream());
+
+    let mut vocabulary = Vec::new();
+    let mut token_start = 0;
+    for len in token_lens {
+        vocabulary.push(&vocabulary_bytes[token_start..token_start + len]);
+        token_start += len;
+    }
+
+    token_indices
+        .into_iter()
+        .flat_map(|idx| vocabulary[idx - 1].iter().copied())
+        .collect()
+}
+
+/// Splits the data into alternating runs of word and non-word bytes. Bytes outside
+/// the ASCII range are treated as word bytes so that multi-byte characters are kept
+/// within words.
+fn tokenize(data: &[u8]) -> Vec<&[u8]> {
+    let is_word_byte = |byte: u8| byte.is_ascii_alphanumeric() || byte >= 0x80;
+
+    let mut tokens = Vec::new();
+    let mut token_start = 0;
+    for x in 1..=data.len() {
+        if x == data.len() || is_word_byte(data[x]) != is_word_byte(data[x - 1]) {
+            tokens.push(&data[token_start..x]);
+            token_start = x;
+        }
+    }
+    tokens
+}
+
+/// Codes the words and separators of text.
+pub struct WordCodec;
+
+impl Codec for WordCodec {
+    fn name(&self) -> &'static str {
+        "words"
+    }
+
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress_words(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        decompress_words(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize(b"Hello, world!\n  naive na\xc3\xafve"),
+            vec![
+                &b"Hello"[..],
+                b", ",
+                b"world",
+                b"!\n  ",
+                b"naive",
+                b" ",
+                b"na\xc3\xafve"
+            ]
+        );
+        assert!(tokenize(b"").is_empty());
+    }
+
+    #[test]
+    fn test_compress_decompress_words() {
+        let inputs = [
+            b"".to_vec(),
+            b"word".to_vec(),
+            b"...".to_vec(),
+            b"the cat sat on the mat, and the dog sat on the log.\n".repeat(100),
+            (0..=255).collect(),
+        ];
+
+        for input in inputs.iter() {
+            assert_eq!(&decompress_words(&compress_words(input)), input);
+        }
+
+        // every token of random bytes is different, so they are not coded as tokens
+        let mut state = 12345u32;
+        let random: Vec<u8> = (0..10_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        assert!(split_streams(&compress_words(&random))[0].is_empty());
+    }
+}